@@ -0,0 +1,97 @@
+//! Derive macro for [`ark_circom::CircomInputs`]
+//!
+//! ```ignore
+//! #[derive(CircomInputs)]
+//! struct Inputs {
+//!     leaf: Fr,
+//!     #[circom(rename = "pathElements")]
+//!     path_elements: [Fr; 20],
+//! }
+//! ```
+//!
+//! Each named field becomes the input signal of the same name, unless renamed with
+//! `#[circom(rename = "...")]`, and its values are flattened in circom's row-major order.
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Lit, Meta, NestedMeta, Result};
+
+#[proc_macro_derive(CircomInputs, attributes(circom))]
+pub fn derive_circom_inputs(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    Span::call_site(),
+                    "CircomInputs can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "CircomInputs can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut inserts = Vec::with_capacity(fields.len());
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let name = match signal_name(&field.attrs)? {
+            Some(name) => name,
+            None => ident.to_string().trim_start_matches("r#").to_string(),
+        };
+        inserts.push(quote! {
+            inputs.insert(
+                #name.to_string(),
+                ::ark_circom::CircomInputValue::to_values(&self.#ident),
+            );
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::ark_circom::CircomInputs for #ident #ty_generics #where_clause {
+            fn to_inputs(
+                &self,
+            ) -> ::std::collections::HashMap<::std::string::String, ::std::vec::Vec<::ark_circom::num_bigint::BigInt>> {
+                let mut inputs = ::std::collections::HashMap::new();
+                #(#inserts)*
+                inputs
+            }
+        }
+    })
+}
+
+// reads `#[circom(rename = "name")]`
+fn signal_name(attrs: &[syn::Attribute]) -> Result<Option<String>> {
+    let mut name = None;
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("circom")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new_spanned(meta, "expected #[circom(...)]")),
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename") => {
+                    match nv.lit {
+                        Lit::Str(lit) => name = Some(lit.value()),
+                        lit => return Err(Error::new_spanned(lit, "expected a string")),
+                    }
+                }
+                other => return Err(Error::new_spanned(other, "unknown circom attribute")),
+            }
+        }
+    }
+    Ok(name)
+}
@@ -1,12 +1,20 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
-use ark_circom::{read_zkey, CircomReduction, WitnessCalculator};
+use ark_circom::{
+    read_zkey, small_scalar_msm, CircomBuilder, CircomConfig, CircomReduction, PreparedProver,
+    WitnessCalculator,
+};
 use ark_std::rand::thread_rng;
 
-use ark_bn254::Bn254;
-use ark_groth16::{create_proof_with_reduction_and_matrices, prepare_verifying_key, verify_proof};
+use ark_bn254::{Bn254, Fr};
+use ark_ec::msm::VariableBaseMSM;
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::{
+    create_proof_with_reduction_and_matrices, generate_random_parameters, prepare_verifying_key,
+    verify_proof,
+};
 
-use std::{collections::HashMap, fs::File};
+use std::{collections::HashMap, fs::File, path::Path};
 
 fn bench_groth(c: &mut Criterion, num_validators: u32, num_constraints: u32) {
     let i = num_validators;
@@ -15,6 +23,11 @@ fn bench_groth(c: &mut Criterion, num_validators: u32, num_constraints: u32) {
         "./test-vectors/complex-circuit/complex-circuit-{}-{}.zkey",
         i, j
     );
+    if !Path::new(&path).exists() {
+        // the zkey is built by `test-vectors/complex-circuit/build.sh`
+        eprintln!("skipping groth proof {} {}, {} not found", i, j, path);
+        return;
+    }
     let mut file = File::open(&path).unwrap();
     let (params, matrices) = read_zkey(&mut file).unwrap();
     let num_inputs = matrices.num_instance_variables;
@@ -79,6 +92,101 @@ fn bench_groth(c: &mut Criterion, num_validators: u32, num_constraints: u32) {
     });
 }
 
+// compares `PreparedProver` with arkworks' prover, with random parameters for the complex
+// circuit as its zkey is only built by `complex-circuit/build.sh`
+fn bench_prepared(c: &mut Criterion) {
+    let cfg = CircomConfig::<Bn254>::new(
+        "./test-vectors/complex-circuit/complex-circuit-10000-10000.wasm",
+        "./test-vectors/complex-circuit/complex-circuit-10000-10000.r1cs",
+    )
+    .unwrap();
+    let mut builder = CircomBuilder::new(cfg);
+    builder.push_input("a", 3);
+
+    let mut rng = thread_rng();
+    let params = generate_random_parameters::<Bn254, _, _>(builder.setup(), &mut rng).unwrap();
+    let circom = builder.build().unwrap();
+    let matrices = circom.r1cs.to_matrices();
+    let full_assignment = circom
+        .r1cs
+        .permute_witness(circom.witness.as_ref().unwrap());
+    let num_inputs = matrices.num_instance_variables;
+    let num_constraints = matrices.num_constraints;
+
+    use ark_std::UniformRand;
+    let r = Fr::rand(&mut rng);
+    let s = Fr::rand(&mut rng);
+
+    c.bench_function("groth proof complex 10000 10000 arkworks", |b| {
+        b.iter(|| {
+            black_box(
+                create_proof_with_reduction_and_matrices::<_, CircomReduction>(
+                    &params,
+                    r,
+                    s,
+                    &matrices,
+                    num_inputs,
+                    num_constraints,
+                    &full_assignment,
+                )
+                .unwrap(),
+            )
+        })
+    });
+
+    // the MSMs of the witness of the circuit, which only has large values, of a witness
+    // decomposing each value into bits as `Num2Bits` does, as most circom witnesses do, and
+    // of a witness of 16-bit values such as selectors and indices
+    let scalars = full_assignment
+        .iter()
+        .map(|scalar| scalar.into_repr())
+        .collect::<Vec<_>>();
+    let bits = scalars
+        .chunks(255)
+        .flat_map(|chunk| {
+            let value = chunk[0];
+            std::iter::once(value).chain(
+                (0..chunk.len() - 1).map(move |i| Fr::from(value.get_bit(i) as u64).into_repr()),
+            )
+        })
+        .collect::<Vec<_>>();
+    let u16s = (0..scalars.len())
+        .map(|i| Fr::from(i as u64 % (1 << 16)).into_repr())
+        .collect::<Vec<_>>();
+    for (name, scalars) in [("large", &scalars), ("bits", &bits), ("u16", &u16s)] {
+        c.bench_function(&format!("msm complex 10000 10000 {} arkworks", name), |b| {
+            b.iter(|| {
+                black_box((
+                    VariableBaseMSM::multi_scalar_mul(&params.a_query, scalars),
+                    VariableBaseMSM::multi_scalar_mul(&params.b_g2_query, scalars),
+                ))
+            })
+        });
+        c.bench_function(
+            &format!("msm complex 10000 10000 {} small scalars", name),
+            |b| {
+                b.iter(|| {
+                    black_box((
+                        small_scalar_msm(&params.a_query, scalars),
+                        small_scalar_msm(&params.b_g2_query, scalars),
+                    ))
+                })
+            },
+        );
+    }
+
+    let mut prover = PreparedProver::new(params, matrices).unwrap();
+    c.bench_function("groth proof complex 10000 10000 prepared", |b| {
+        b.iter(|| black_box(prover.create_proof(r, s, &full_assignment).unwrap()))
+    });
+
+    let mut prover = prover.with_fixed_base_tables();
+    c.bench_function(
+        "groth proof complex 10000 10000 prepared with tables",
+        |b| b.iter(|| black_box(prover.create_proof(r, s, &full_assignment).unwrap())),
+    );
+}
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "bench-complex-all")] {
         const MIN_NUM_VARIABLES_POWER: u32 = 3;
@@ -93,12 +201,12 @@ cfg_if::cfg_if! {
                 }
             }
         }
-        criterion_group!(benches, groth_all);
+        criterion_group!(benches, groth_all, bench_prepared);
     } else {
       fn groth(c: &mut Criterion) {
         bench_groth(c, 10000, 10000);
       }
-      criterion_group!(benches, groth);
+      criterion_group!(benches, groth, bench_prepared);
     }
 }
 
@@ -0,0 +1,44 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use ark_circom::{read_zkey, read_zkey_with_validation, Validation};
+
+use std::{fs::File, io::BufReader, path::Path};
+
+fn bench_load(c: &mut Criterion, name: &str, path: &str) {
+    if !Path::new(path).exists() {
+        // the complex circuit's zkey is built by `test-vectors/complex-circuit/build.sh`
+        eprintln!("skipping {}, {} not found", name, path);
+        return;
+    }
+
+    let load = |validation| {
+        let mut reader = BufReader::new(File::open(path).unwrap());
+        read_zkey_with_validation(&mut reader, validation).unwrap()
+    };
+    load(Validation::Full);
+
+    c.bench_function(&format!("load zkey {}", name), |b| {
+        b.iter(|| {
+            let mut reader = BufReader::new(File::open(path).unwrap());
+            black_box(read_zkey(&mut reader).unwrap());
+        })
+    });
+    c.bench_function(&format!("load zkey {} unchecked", name), |b| {
+        b.iter(|| black_box(load(Validation::Unchecked)))
+    });
+    c.bench_function(&format!("load zkey {} full validation", name), |b| {
+        b.iter(|| black_box(load(Validation::Full)))
+    });
+}
+
+fn zkey(c: &mut Criterion) {
+    bench_load(c, "test", "./test-vectors/test.zkey");
+    bench_load(
+        c,
+        "complex 10000 10000",
+        "./test-vectors/complex-circuit/complex-circuit-10000-10000.zkey",
+    );
+}
+
+criterion_group!(benches, zkey);
+criterion_main!(benches);
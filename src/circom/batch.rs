@@ -0,0 +1,179 @@
+//! Proving many statements about the same circuit concurrently
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::{Proof, ProvingKey, VerifyingKey};
+use ark_std::rand::thread_rng;
+use color_eyre::{eyre::eyre, Result};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, PoisonError,
+    },
+};
+
+use super::{prover::Circuit, CircomProver, PreparedProver, PublicSignals};
+use crate::{witness::CircomInputs, WitnessCalculatorPool};
+
+type ProofResult = Result<(Proof<Bn254>, PublicSignals<Fr>)>;
+
+/// Proves statements about a circuit for many inputs at once, e.g. one proof per user of an
+/// airdrop
+///
+/// The proofs are split into jobs, each proving one input after the other with its own
+/// share of the threads, instead of every proof using all of them in turn: the FFTs and
+/// MSMs of a proof don't scale linearly with the number of threads, and the witnesses,
+/// computed by a single thread each, are computed concurrently across a
+/// [`WitnessCalculatorPool`].
+///
+/// The proving key is shared by the jobs, but each job holds a WASM instance, a witness and
+/// the buffers of a [`PreparedProver`], about `3·n + m` field elements for a domain of
+/// size `n` and `m` variables. By default there is one job per thread, which gives the
+/// most proofs per second; [`BatchProver::with_jobs`] trades some of them for less memory.
+/// Each job runs in its own rayon pool, built once with the prover.
+///
+/// ```rust,ignore
+/// let prover = CircomProver::new("circuit.zkey", "circuit.wasm")?.with_r1cs("circuit.r1cs")?;
+/// let batch = BatchProver::from_prover(prover)?;
+/// for (user, result) in users.iter().zip(batch.prove(&inputs)) {
+///     match result {
+///         Ok((proof, public_signals)) => /* ... */,
+///         Err(err) => eprintln!("no proof for {}: {}", user, err),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct BatchProver {
+    prover: PreparedProver<Bn254>,
+    circuit: Circuit,
+    witnesses: WitnessCalculatorPool,
+    // one per job
+    pools: Vec<ThreadPool>,
+}
+
+impl BatchProver {
+    /// Loads the zkey and the wasm of the circuit, see [`CircomProver::new`]
+    pub fn new(zkey: impl AsRef<Path>, wasm: impl AsRef<Path>) -> Result<Self> {
+        Self::from_prover(CircomProver::new(zkey, wasm)?)
+    }
+
+    /// Proves with the key, the circuit and the fixed-base tables of `prover`, with one job
+    /// per thread of the current rayon pool
+    pub fn from_prover(prover: CircomProver) -> Result<Self> {
+        Self::with_jobs(prover, rayon::current_num_threads())
+    }
+
+    /// Same as [`BatchProver::from_prover`], running at most `jobs` proofs concurrently. The
+    /// threads of the current rayon pool, or one per job if there are more jobs, are split
+    /// between the jobs.
+    pub fn with_jobs(prover: CircomProver, jobs: usize) -> Result<Self> {
+        let witnesses = WitnessCalculatorPool::from_calculator(prover.wtns, jobs)?;
+        let jobs = witnesses.size();
+        let num_threads = rayon::current_num_threads().max(jobs);
+        let pools = (0..jobs)
+            .map(|job| {
+                // spread the remainder of the threads over the first jobs
+                let threads = num_threads / jobs + usize::from(job < num_threads % jobs);
+                ThreadPoolBuilder::new().num_threads(threads).build()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            prover: prover.prover,
+            circuit: prover.circuit,
+            witnesses,
+            pools,
+        })
+    }
+
+    /// The number of proofs computed concurrently
+    pub fn jobs(&self) -> usize {
+        self.witnesses.size()
+    }
+
+    pub fn proving_key(&self) -> &ProvingKey<Bn254> {
+        self.prover.proving_key()
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey<Bn254> {
+        &self.proving_key().vk
+    }
+
+    /// Proves the statement for each of the inputs, returning the proofs in the order of
+    /// the inputs, or the error which prevented each of them, see [`CircomProver::prove`].
+    /// A panic while proving an input is returned as its error.
+    ///
+    /// Fewer than [`BatchProver::jobs`] jobs run when there are fewer inputs.
+    pub fn prove<I: CircomInputs + Sync>(
+        &self,
+        inputs: &[I],
+    ) -> Vec<Result<(Proof<Bn254>, PublicSignals<Fr>)>> {
+        let jobs = self.jobs().min(inputs.len());
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(inputs.len()));
+        in_pools(&self.pools[..jobs], &|| {
+            self.run_job(inputs, &next, &results)
+        });
+
+        let mut results = results.into_inner().unwrap_or_else(PoisonError::into_inner);
+        results.sort_unstable_by_key(|(i, _)| *i);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// Verifies the proof against the public signals
+    pub fn verify(&self, proof: &Proof<Bn254>, public_signals: &[Fr]) -> Result<bool> {
+        self.circuit.verify(proof, public_signals)
+    }
+
+    // proves the inputs at the indices taken from `next` until there are none left
+    fn run_job<I: CircomInputs + Sync>(
+        &self,
+        inputs: &[I],
+        next: &AtomicUsize,
+        results: &Mutex<Vec<(usize, ProofResult)>>,
+    ) {
+        // shares the key with `self.prover`, but has its own buffers, which every proof
+        // overwrites, so that they can be reused after a panic
+        let mut prover = self.prover.clone();
+
+        loop {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            if i >= inputs.len() {
+                return;
+            }
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                // the calculator goes back to the pool before proving
+                let witness = self
+                    .circuit
+                    .witness(&mut self.witnesses.get(), &inputs[i])?;
+                self.circuit.prove(&mut prover, &witness, &mut thread_rng())
+            }))
+            .unwrap_or_else(|payload| Err(eyre!("proving panicked: {}", panic_message(&*payload))));
+            results
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push((i, result));
+        }
+    }
+}
+
+// runs `job` once in each of the pools, returning when they have all finished
+fn in_pools(pools: &[ThreadPool], job: &(dyn Fn() + Sync)) {
+    if let Some((pool, rest)) = pools.split_first() {
+        // the scope runs on the current thread, so that all the pools are entered at once
+        pool.in_place_scope(|scope| {
+            scope.spawn(|_| job());
+            in_pools(rest, job);
+        });
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
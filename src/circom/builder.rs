@@ -1,13 +1,16 @@
 use ark_ec::PairingEngine;
 use std::{fs::File, path::Path};
 
-use super::{CircomCircuit, R1CS};
+use super::{check_constraints, public::names_from_sym, CircomCircuit, R1CS};
 
 use num_bigint::BigInt;
 use std::collections::HashMap;
 
-use crate::{circom::R1CSFile, witness::WitnessCalculator};
-use color_eyre::Result;
+use crate::{
+    circom::{R1CSFile, SymFile},
+    witness::{parse_inputs_json, CircomInputs, InputSignals, Witness, WitnessCalculator},
+};
+use color_eyre::{eyre::eyre, Result};
 
 #[derive(Clone, Debug)]
 pub struct CircomBuilder<E: PairingEngine> {
@@ -21,17 +24,31 @@ pub struct CircomConfig<E: PairingEngine> {
     pub r1cs: R1CS<E>,
     pub wtns: WitnessCalculator,
     pub sanity_check: bool,
+    /// Checks the witness against every constraint before returning the circuit from
+    /// [`CircomBuilder::build`], failing with a report of the unsatisfied constraints. Debug
+    /// builds always check it.
+    pub check_constraints: bool,
+    /// Symbol table used to validate the inputs when the runtime can't report them
+    pub sym: Option<SymFile>,
 }
 
 impl<E: PairingEngine> CircomConfig<E> {
+    /// Also loads the `.sym` file next to the R1CS, if there is one
     pub fn new(wtns: impl AsRef<Path>, r1cs: impl AsRef<Path>) -> Result<Self> {
         let wtns = WitnessCalculator::new(wtns).unwrap();
+        let sym_path = r1cs.as_ref().with_extension("sym");
         let reader = File::open(r1cs)?;
         let r1cs = R1CSFile::new(reader)?.into();
+        let sym = match File::open(sym_path) {
+            Ok(reader) => Some(SymFile::new(reader)?),
+            Err(_) => None,
+        };
         Ok(Self {
             wtns,
             r1cs,
             sanity_check: false,
+            check_constraints: false,
+            sym,
         })
     }
 }
@@ -52,13 +69,84 @@ impl<E: PairingEngine> CircomBuilder<E> {
         values.push(val.into());
     }
 
+    /// Pushes every input of a JSON object in the `input.json` format accepted by snarkjs,
+    /// see [`crate::parse_inputs_json`]
+    pub fn push_inputs_json(&mut self, json: &str) -> Result<()> {
+        for (name, values) in parse_inputs_json(json)? {
+            self.inputs
+                .entry(name)
+                .or_insert_with(Vec::new)
+                .extend(values);
+        }
+        Ok(())
+    }
+
+    /// Pushes every input of a typed set of inputs, see [`crate::CircomInputs`]
+    pub fn push_inputs(&mut self, inputs: &impl CircomInputs) {
+        for (name, values) in inputs.to_inputs() {
+            self.inputs
+                .entry(name)
+                .or_insert_with(Vec::new)
+                .extend(values);
+        }
+    }
+
+    /// Returns whether the inputs can be validated, i.e. whether the runtime reports the
+    /// sizes of the input signals or the circuit's `.sym` file was found
+    pub fn can_validate_inputs(&self) -> bool {
+        self.cfg.wtns.can_validate_inputs() || self.cfg.sym.is_some()
+    }
+
+    /// Checks the inputs pushed so far against the circuit's input signals, using the
+    /// sizes reported by the runtime when available and the `.sym` file otherwise.
+    /// Fails if neither is available, see [`CircomBuilder::can_validate_inputs`].
+    pub fn validate_inputs(&self) -> Result<()> {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|(name, values)| (name.as_str(), values.len()));
+
+        #[cfg(feature = "circom-2")]
+        if self.cfg.wtns.can_validate_inputs() {
+            return self.cfg.wtns.validate_inputs(inputs);
+        }
+
+        let sym = self.cfg.sym.as_ref().ok_or_else(|| {
+            eyre!("cannot validate the inputs: no .sym file and the runtime doesn't report them")
+        })?;
+        let r1cs = &self.cfg.r1cs;
+        let num_inputs = r1cs.num_inputs - 1 - r1cs.num_outputs + r1cs.num_private_inputs;
+        InputSignals::from_sym(sym, r1cs.num_outputs, num_inputs).validate(inputs)?;
+        Ok(())
+    }
+
+    /// Calculates the witness for the inputs pushed so far, with its signals named after
+    /// the circuit's `.sym` file
+    pub fn witness(&mut self) -> Result<Witness> {
+        let sym = self
+            .cfg
+            .sym
+            .clone()
+            .ok_or_else(|| eyre!("a .sym file is needed to name the witness signals"))?;
+        self.validate_inputs()?;
+        let values = self
+            .cfg
+            .wtns
+            .calculate_witness(self.inputs.clone(), self.cfg.sanity_check)?;
+        Ok(Witness::new(values, sym))
+    }
+
     /// Generates an empty circom circuit with no witness set, to be used for
     /// generation of the trusted setup parameters
     pub fn setup(&self) -> CircomCircuit<E> {
-        let mut circom = CircomCircuit {
-            r1cs: self.cfg.r1cs.clone(),
-            witness: None,
-        };
+        // the witness is laid out as the R1CS wires, public signals first
+        let public_signal_names = self.cfg.sym.as_ref().and_then(|sym| {
+            let indices = (1..self.cfg.r1cs.num_inputs).collect::<Vec<_>>();
+            names_from_sym(sym, &indices)
+        });
+
+        let mut circom = CircomCircuit::new(self.cfg.r1cs.clone(), None);
+        circom.public_signal_names = public_signal_names;
 
         // Disable the wire mapping
         circom.r1cs.wire_mapping = None;
@@ -67,8 +155,12 @@ impl<E: PairingEngine> CircomBuilder<E> {
     }
 
     /// Creates the circuit populated with the witness corresponding to the previously
-    /// provided inputs
+    /// provided inputs, which are first validated if [`CircomBuilder::can_validate_inputs`]
     pub fn build(mut self) -> Result<CircomCircuit<E>> {
+        if self.can_validate_inputs() {
+            self.validate_inputs()?;
+        }
+
         let mut circom = self.setup();
 
         // calculate the witness
@@ -76,23 +168,12 @@ impl<E: PairingEngine> CircomBuilder<E> {
             .cfg
             .wtns
             .calculate_witness_element::<E, _>(self.inputs, self.cfg.sanity_check)?;
-        circom.witness = Some(witness);
+        // sanity check, always done in debug builds
+        if self.cfg.check_constraints || cfg!(debug_assertions) {
+            check_constraints(&circom.r1cs, &witness, self.cfg.sym.as_ref())?;
+        }
 
-        // sanity check
-        debug_assert!({
-            use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
-            let cs = ConstraintSystem::<E::Fr>::new_ref();
-            circom.clone().generate_constraints(cs.clone()).unwrap();
-            let is_satisfied = cs.is_satisfied().unwrap();
-            if !is_satisfied {
-                println!(
-                    "Unsatisfied constraint: {:?}",
-                    cs.which_is_unsatisfied().unwrap()
-                );
-            }
-
-            is_satisfied
-        });
+        circom.witness = Some(witness);
 
         Ok(circom)
     }
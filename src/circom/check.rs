@@ -0,0 +1,375 @@
+//! Satisfiability check of a witness against the circuit's R1CS, reporting the failing
+//! constraints with the signal names of the `.sym` file
+use std::fmt;
+
+use ark_ec::PairingEngine;
+use ark_ff::{PrimeField, Zero};
+use num_bigint::BigUint;
+use rayon::prelude::*;
+
+use super::{LinearCombination, SymFile, R1CS};
+
+/// A term `coeff·signal` of one of the linear combinations of a constraint
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Term<F> {
+    pub coeff: F,
+    /// The signal's name, `one` for the constant wire or `w[i]` if it is unknown
+    pub signal: String,
+    pub value: F,
+}
+
+/// A constraint `A·B = C` which the witness does not satisfy
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsatisfiedConstraint<F> {
+    /// Position of the constraint in the R1CS file
+    pub index: usize,
+    /// Path of the component instance the constraint was likely generated in, e.g.
+    /// `main.levels[3]`, guessed from the names of its signals
+    ///
+    /// Neither the R1CS nor the `.sym` file records the template a constraint comes from, so
+    /// this is the shallowest component among the signals, which is wrong for constraints
+    /// only involving the signals of sub-components.
+    pub component_path: Option<String>,
+    pub a: Vec<Term<F>>,
+    pub b: Vec<Term<F>>,
+    pub c: Vec<Term<F>>,
+    pub a_value: F,
+    pub b_value: F,
+    pub c_value: F,
+}
+
+/// Every constraint of the circuit not satisfied by a witness
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsatisfiedConstraints<F> {
+    pub constraints: Vec<UnsatisfiedConstraint<F>>,
+}
+
+/// Error returned by [`check_constraints`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckError<F> {
+    /// The witness does not have a value for every wire, e.g. it was calculated by the wasm
+    /// of another circuit
+    WitnessLength {
+        expected: usize,
+        actual: usize,
+    },
+    Unsatisfied(UnsatisfiedConstraints<F>),
+}
+
+/// Checks that `witness` satisfies every constraint of `r1cs`, naming the signals of the
+/// failing ones after `sym` if provided
+///
+/// The witness must have a value for each wire, or for each label the wires are mapped to.
+pub fn check_constraints<E: PairingEngine>(
+    r1cs: &R1CS<E>,
+    witness: &[E::Fr],
+    sym: Option<&SymFile>,
+) -> Result<(), CheckError<E::Fr>> {
+    let expected = match &r1cs.wire_mapping {
+        Some(m) => m.iter().max().map_or(0, |label| label + 1),
+        None => r1cs.num_variables,
+    };
+    // a witness indexed by label may also hold the values of unused labels
+    let length_matches = match &r1cs.wire_mapping {
+        Some(_) => witness.len() >= expected,
+        None => witness.len() == expected,
+    };
+    if !length_matches {
+        return Err(CheckError::WitnessLength {
+            expected,
+            actual: witness.len(),
+        });
+    }
+
+    let value = |wire: usize| match &r1cs.wire_mapping {
+        Some(m) => witness[m[wire]],
+        None => witness[wire],
+    };
+    let eval = |lc: LinearCombination<E::Fr>| {
+        lc.iter().fold(E::Fr::zero(), |acc, (wire, coeff)| {
+            acc + coeff * value(wire)
+        })
+    };
+
+    let failing = r1cs
+        .constraints
+        .par_iter()
+        .enumerate()
+        .filter_map(|(i, constraint)| {
+            let (a, b, c) = (eval(constraint.a), eval(constraint.b), eval(constraint.c));
+            if a * b == c {
+                None
+            } else {
+                Some((i, a, b, c))
+            }
+        })
+        .collect::<Vec<_>>();
+    if failing.is_empty() {
+        return Ok(());
+    }
+
+    let names = sym
+        .map(|sym| sym.names_by_witness_index(0..witness.len()))
+        .unwrap_or_default();
+    let name = |wire: usize| {
+        let index = match &r1cs.wire_mapping {
+            Some(m) => m[wire],
+            None => wire,
+        };
+        match names.get(&index) {
+            _ if index == 0 => "one".to_string(),
+            Some(name) => name.to_string(),
+            None => format!("w[{}]", index),
+        }
+    };
+    let terms = |lc: LinearCombination<E::Fr>| {
+        lc.iter()
+            .map(|(wire, coeff)| Term {
+                coeff,
+                signal: name(wire),
+                value: value(wire),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let constraints = failing
+        .into_iter()
+        .map(|(index, a_value, b_value, c_value)| {
+            let constraint = r1cs.constraints.get(index).expect("failing constraint");
+            let (a, b, c) = (
+                terms(constraint.a),
+                terms(constraint.b),
+                terms(constraint.c),
+            );
+            let component_path = component_path(a.iter().chain(&b).chain(&c).filter_map(|t| {
+                if names.is_empty() || t.signal == "one" {
+                    None
+                } else {
+                    Some(t.signal.as_str())
+                }
+            }));
+            UnsatisfiedConstraint {
+                index,
+                component_path,
+                a,
+                b,
+                c,
+                a_value,
+                b_value,
+                c_value,
+            }
+        })
+        .collect();
+
+    Err(CheckError::Unsatisfied(UnsatisfiedConstraints {
+        constraints,
+    }))
+}
+
+// a template's constraints involve its own signals and the inputs and outputs of its
+// direct sub-components, so it is the shallowest component among the signals
+fn component_path<'a>(signals: impl Iterator<Item = &'a str>) -> Option<String> {
+    signals
+        .filter_map(|name| name.rfind('.').map(|i| &name[..i]))
+        .min_by_key(|component| component.matches('.').count())
+        .map(|component| component.to_string())
+}
+
+impl<F: PrimeField> fmt::Display for UnsatisfiedConstraint<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "constraint {}", self.index)?;
+        if let Some(path) = &self.component_path {
+            write!(f, " in {}", path)?;
+        }
+        writeln!(f, ": A·B ≠ C")?;
+        for (name, lc, value) in [
+            ("A", &self.a, &self.a_value),
+            ("B", &self.b, &self.b_value),
+            ("C", &self.c, &self.c_value),
+        ] {
+            writeln!(f, "  {} = {} = {}", name, fmt_lc(lc), fmt_field(value))?;
+        }
+        write!(
+            f,
+            "  {} · {} = {} ≠ {}",
+            fmt_field(&self.a_value),
+            fmt_field(&self.b_value),
+            fmt_field(&(self.a_value * self.b_value)),
+            fmt_field(&self.c_value)
+        )
+    }
+}
+
+impl<F: PrimeField> fmt::Display for UnsatisfiedConstraints<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} unsatisfied constraints", self.constraints.len())?;
+        for constraint in &self.constraints {
+            write!(f, "\n{}", constraint)?;
+        }
+        Ok(())
+    }
+}
+
+impl<F: PrimeField> std::error::Error for UnsatisfiedConstraints<F> {}
+
+impl<F: PrimeField> fmt::Display for CheckError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckError::WitnessLength { expected, actual } => write!(
+                f,
+                "the witness has {} values but the circuit needs {}",
+                actual, expected
+            ),
+            CheckError::Unsatisfied(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<F: PrimeField> std::error::Error for CheckError<F> {}
+
+// `2·main.a[3] + main.b[11]`, with each signal's value in brackets
+fn fmt_lc<F: PrimeField>(lc: &[Term<F>]) -> String {
+    if lc.is_empty() {
+        return "0".to_string();
+    }
+
+    lc.iter()
+        .map(|term| {
+            let coeff = fmt_field(&term.coeff);
+            if term.signal == "one" {
+                coeff
+            } else if term.coeff.is_one() {
+                format!("{}[{}]", term.signal, fmt_field(&term.value))
+            } else {
+                format!("{}·{}[{}]", coeff, term.signal, fmt_field(&term.value))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+// field elements closer to p than to 0 are shown as negative numbers, as circom does
+fn fmt_field<F: PrimeField>(value: &F) -> String {
+    let value: BigUint = value.into_repr().into();
+    let neg: BigUint = (-F::one()).into_repr().into();
+    let neg = neg + 1u32 - &value;
+    if neg < value {
+        format!("-{}", neg)
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Bn254, Fr};
+
+    fn r1cs() -> R1CS<Bn254> {
+        // one, main.c, main.a, main.b, main.sub.x with a·b = c and 2·a + 1 = x
+        R1CS {
+            num_inputs: 2,
+            num_outputs: 1,
+            num_private_inputs: 2,
+            num_aux: 3,
+            num_variables: 5,
+            constraints: vec![
+                (
+                    vec![(2, Fr::from(1u64))],
+                    vec![(3, Fr::from(1u64))],
+                    vec![(1, Fr::from(1u64))],
+                ),
+                (
+                    vec![],
+                    vec![],
+                    vec![
+                        (0, Fr::from(1u64)),
+                        (2, Fr::from(2u64)),
+                        (4, -Fr::from(1u64)),
+                    ],
+                ),
+            ]
+            .into(),
+            wire_mapping: None,
+        }
+    }
+
+    fn sym() -> SymFile {
+        let sym = "1,1,0,main.c\n2,2,0,main.a\n3,3,0,main.b\n4,4,1,main.sub.x\n";
+        SymFile::new(sym.as_bytes()).unwrap()
+    }
+
+    fn witness(values: &[u64]) -> Vec<Fr> {
+        values.iter().map(|v| Fr::from(*v)).collect()
+    }
+
+    #[test]
+    fn satisfied() {
+        check_constraints(&r1cs(), &witness(&[1, 33, 3, 11, 7]), Some(&sym())).unwrap();
+    }
+
+    #[test]
+    fn unsatisfied() {
+        let err = match check_constraints(&r1cs(), &witness(&[1, 34, 3, 11, 8]), Some(&sym())) {
+            Err(CheckError::Unsatisfied(err)) => err,
+            res => panic!("{:?}", res),
+        };
+        assert_eq!(err.constraints.len(), 2);
+
+        let first = &err.constraints[0];
+        assert_eq!(first.index, 0);
+        assert_eq!(first.component_path.as_deref(), Some("main"));
+        assert_eq!(first.c[0].signal, "main.c");
+        assert_eq!(
+            first.to_string(),
+            "constraint 0 in main: A·B ≠ C\n  \
+             A = main.a[3] = 3\n  \
+             B = main.b[11] = 11\n  \
+             C = main.c[34] = 34\n  \
+             3 · 11 = 33 ≠ 34"
+        );
+
+        let second = &err.constraints[1];
+        assert_eq!(second.index, 1);
+        assert_eq!(
+            second.to_string().lines().nth(3).unwrap(),
+            "  C = 1 + 2·main.a[3] + -1·main.sub.x[8] = -1"
+        );
+    }
+
+    #[test]
+    fn unnamed() {
+        let err = match check_constraints(&r1cs(), &witness(&[1, 34, 3, 11, 7]), None) {
+            Err(CheckError::Unsatisfied(err)) => err,
+            res => panic!("{:?}", res),
+        };
+        assert_eq!(err.constraints.len(), 1);
+        assert_eq!(err.constraints[0].component_path, None);
+        assert_eq!(err.constraints[0].c[0].signal, "w[1]");
+    }
+
+    #[test]
+    fn witness_length() {
+        let err = check_constraints(&r1cs(), &witness(&[1, 33, 3, 11]), None).unwrap_err();
+        assert_eq!(
+            err,
+            CheckError::WitnessLength {
+                expected: 5,
+                actual: 4
+            }
+        );
+
+        // a mapped witness is indexed by label
+        let mut r1cs = r1cs();
+        r1cs.wire_mapping = Some(vec![0, 1, 2, 3, 6]);
+        let err = check_constraints(&r1cs, &witness(&[1, 33, 3, 11, 7, 0]), None).unwrap_err();
+        assert_eq!(
+            err,
+            CheckError::WitnessLength {
+                expected: 7,
+                actual: 6
+            }
+        );
+        check_constraints(&r1cs, &witness(&[1, 33, 3, 11, 0, 0, 7]), None).unwrap();
+    }
+}
@@ -3,7 +3,7 @@ use ark_relations::r1cs::{
     ConstraintSynthesizer, ConstraintSystemRef, LinearCombination, SynthesisError, Variable,
 };
 
-use super::R1CS;
+use super::{PublicSignals, R1CS};
 
 use color_eyre::Result;
 
@@ -11,9 +11,24 @@ use color_eyre::Result;
 pub struct CircomCircuit<E: PairingEngine> {
     pub r1cs: R1CS<E>,
     pub witness: Option<Vec<E::Fr>>,
+    pub(super) public_signal_names: Option<Vec<String>>,
 }
 
 impl<'a, E: PairingEngine> CircomCircuit<E> {
+    pub fn new(r1cs: R1CS<E>, witness: Option<Vec<E::Fr>>) -> Self {
+        Self {
+            r1cs,
+            witness,
+            public_signal_names: None,
+        }
+    }
+
+    /// Names of the public signals, outputs first, when the circuit was built with its
+    /// `.sym` file
+    pub fn public_signal_names(&self) -> Option<&[String]> {
+        self.public_signal_names.as_deref()
+    }
+
     pub fn get_public_inputs(&self) -> Option<Vec<E::Fr>> {
         match &self.witness {
             None => None,
@@ -23,6 +38,16 @@ impl<'a, E: PairingEngine> CircomCircuit<E> {
             },
         }
     }
+
+    /// Returns the public signals split into the circuit's outputs and public inputs,
+    /// named if possible
+    pub fn get_public_signals(&self) -> Option<PublicSignals<E::Fr>> {
+        let signals = PublicSignals::new(self.get_public_inputs()?, self.r1cs.num_outputs);
+        Some(match &self.public_signal_names {
+            Some(names) => signals.with_names(names.clone()),
+            None => signals,
+        })
+    }
 }
 
 impl<E: PairingEngine> ConstraintSynthesizer<E::Fr> for CircomCircuit<E> {
@@ -62,18 +87,18 @@ impl<E: PairingEngine> ConstraintSynthesizer<E::Fr> for CircomCircuit<E> {
                 Variable::Witness(index - self.r1cs.num_inputs)
             }
         };
-        let make_lc = |lc_data: &[(usize, E::Fr)]| {
+        let make_lc = |lc_data: super::LinearCombination<E::Fr>| {
             lc_data.iter().fold(
                 LinearCombination::<E::Fr>::zero(),
-                |lc: LinearCombination<E::Fr>, (index, coeff)| lc + (*coeff, make_index(*index)),
+                |lc: LinearCombination<E::Fr>, (index, coeff)| lc + (coeff, make_index(index)),
             )
         };
 
-        for constraint in &self.r1cs.constraints {
+        for constraint in self.r1cs.constraints.iter() {
             cs.enforce_constraint(
-                make_lc(&constraint.0),
-                make_lc(&constraint.1),
-                make_lc(&constraint.2),
+                make_lc(constraint.a),
+                make_lc(constraint.b),
+                make_lc(constraint.c),
             )?;
         }
 
@@ -0,0 +1,292 @@
+//! Compressed sparse row storage for R1CS constraints
+//!
+//! Each of the A, B and C matrices stores its rows back to back in shared arrays, with
+//! `u32` wire indices and `u32` ids into a coefficient table shared by the three
+//! matrices. Circuits reuse a handful of coefficients (mostly `1` and `-1`) across most
+//! terms, so each distinct coefficient is stored once.
+use rayon::prelude::*;
+use std::{collections::HashMap, convert::TryFrom, hash::Hash, iter::FromIterator};
+
+/// The constraints of an R1CS in compressed sparse row form, see the module docs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsrConstraints<F> {
+    matrices: [CsrMatrix; 3],
+    coeffs: Vec<F>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CsrMatrix {
+    // row `i` spans `offsets[i]..offsets[i + 1]` of `wires` and `coeffs`
+    offsets: Vec<usize>,
+    wires: Vec<u32>,
+    coeffs: Vec<u32>,
+}
+
+impl CsrMatrix {
+    fn with_capacity(rows: usize) -> Self {
+        let mut offsets = Vec::with_capacity(rows + 1);
+        offsets.push(0);
+        Self {
+            offsets,
+            wires: Vec::new(),
+            coeffs: Vec::new(),
+        }
+    }
+
+    fn row(&self, i: usize) -> (&[u32], &[u32]) {
+        let range = self.offsets[i]..self.offsets[i + 1];
+        (&self.wires[range.clone()], &self.coeffs[range])
+    }
+}
+
+/// A linear combination of one constraint, as `(wire, coefficient)` terms
+#[derive(Clone, Copy, Debug)]
+pub struct LinearCombination<'a, F> {
+    wires: &'a [u32],
+    coeffs: &'a [u32],
+    table: &'a [F],
+}
+
+impl<'a, F: Copy> LinearCombination<'a, F> {
+    pub fn len(&self) -> usize {
+        self.wires.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wires.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, F)> + 'a {
+        let table = self.table;
+        self.wires
+            .iter()
+            .zip(self.coeffs)
+            .map(move |(wire, coeff)| (*wire as usize, table[*coeff as usize]))
+    }
+}
+
+/// A constraint `A·B = C`
+#[derive(Clone, Copy, Debug)]
+pub struct Constraint<'a, F> {
+    pub a: LinearCombination<'a, F>,
+    pub b: LinearCombination<'a, F>,
+    pub c: LinearCombination<'a, F>,
+}
+
+impl<F: Copy> CsrConstraints<F> {
+    pub fn len(&self) -> usize {
+        self.matrices[0].offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of distinct coefficients
+    pub fn num_coeffs(&self) -> usize {
+        self.coeffs.len()
+    }
+
+    /// Number of terms of the A, B and C matrices
+    pub fn num_non_zero(&self) -> [usize; 3] {
+        [
+            self.matrices[0].wires.len(),
+            self.matrices[1].wires.len(),
+            self.matrices[2].wires.len(),
+        ]
+    }
+
+    pub fn get(&self, i: usize) -> Option<Constraint<'_, F>> {
+        if i >= self.len() {
+            return None;
+        }
+
+        Some(Constraint {
+            a: self.lc(0, i),
+            b: self.lc(1, i),
+            c: self.lc(2, i),
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Constraint<'_, F>> + '_ {
+        (0..self.len()).map(move |i| self.get(i).expect("in range"))
+    }
+
+    /// Same as [`CsrConstraints::iter`], splitting the constraints between the rayon threads
+    pub fn par_iter(&self) -> impl IndexedParallelIterator<Item = Constraint<'_, F>> + '_
+    where
+        F: Sync,
+    {
+        (0..self.len())
+            .into_par_iter()
+            .map(move |i| self.get(i).expect("in range"))
+    }
+
+    fn lc(&self, matrix: usize, i: usize) -> LinearCombination<'_, F> {
+        let (wires, coeffs) = self.matrices[matrix].row(i);
+        LinearCombination {
+            wires,
+            coeffs,
+            table: &self.coeffs,
+        }
+    }
+}
+
+/// Incrementally builds [`CsrConstraints`], deduplicating coefficients on the way
+#[derive(Clone, Debug)]
+pub struct CsrConstraintsBuilder<F> {
+    matrices: [CsrMatrix; 3],
+    coeffs: Vec<F>,
+    coeff_ids: HashMap<F, u32>,
+}
+
+impl<F: Copy + Eq + Hash> CsrConstraintsBuilder<F> {
+    /// `num_constraints` is only used as a capacity hint
+    pub fn with_capacity(num_constraints: usize) -> Self {
+        Self {
+            matrices: [
+                CsrMatrix::with_capacity(num_constraints),
+                CsrMatrix::with_capacity(num_constraints),
+                CsrMatrix::with_capacity(num_constraints),
+            ],
+            coeffs: Vec::new(),
+            coeff_ids: HashMap::new(),
+        }
+    }
+
+    /// Appends a constraint, given as its A, B and C `(wire, coefficient)` terms
+    ///
+    /// # Panics
+    ///
+    /// If a wire index doesn't fit in a `u32`
+    pub fn push(
+        &mut self,
+        a: impl IntoIterator<Item = (usize, F)>,
+        b: impl IntoIterator<Item = (usize, F)>,
+        c: impl IntoIterator<Item = (usize, F)>,
+    ) {
+        self.push_lc(0, a);
+        self.push_lc(1, b);
+        self.push_lc(2, c);
+    }
+
+    fn push_lc(&mut self, matrix: usize, lc: impl IntoIterator<Item = (usize, F)>) {
+        for (wire, coeff) in lc {
+            let wire = u32::try_from(wire).expect("wire index does not fit in a u32");
+            let id = match self.coeff_ids.get(&coeff) {
+                Some(id) => *id,
+                None => {
+                    let id = self.coeffs.len() as u32;
+                    self.coeffs.push(coeff);
+                    self.coeff_ids.insert(coeff, id);
+                    id
+                }
+            };
+
+            let m = &mut self.matrices[matrix];
+            m.wires.push(wire);
+            m.coeffs.push(id);
+        }
+
+        let m = &mut self.matrices[matrix];
+        m.offsets.push(m.wires.len());
+    }
+
+    pub fn build(self) -> CsrConstraints<F> {
+        let mut matrices = self.matrices;
+        for m in &mut matrices {
+            m.offsets.shrink_to_fit();
+            m.wires.shrink_to_fit();
+            m.coeffs.shrink_to_fit();
+        }
+
+        CsrConstraints {
+            matrices,
+            coeffs: self.coeffs,
+        }
+    }
+}
+
+impl<F: Copy + Eq + Hash> Default for CsrConstraints<F> {
+    fn default() -> Self {
+        CsrConstraintsBuilder::with_capacity(0).build()
+    }
+}
+
+impl<F: Copy + Eq + Hash> FromIterator<(Vec<(usize, F)>, Vec<(usize, F)>, Vec<(usize, F)>)>
+    for CsrConstraints<F>
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (Vec<(usize, F)>, Vec<(usize, F)>, Vec<(usize, F)>)>,
+    {
+        let iter = iter.into_iter();
+        let mut builder = CsrConstraintsBuilder::with_capacity(iter.size_hint().0);
+        for (a, b, c) in iter {
+            builder.push(a, b, c);
+        }
+        builder.build()
+    }
+}
+
+impl<F: Copy + Eq + Hash> From<Vec<(Vec<(usize, F)>, Vec<(usize, F)>, Vec<(usize, F)>)>>
+    for CsrConstraints<F>
+{
+    fn from(constraints: Vec<(Vec<(usize, F)>, Vec<(usize, F)>, Vec<(usize, F)>)>) -> Self {
+        constraints.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    #[test]
+    fn roundtrip() {
+        let one = Fr::from(1u64);
+        let constraints = vec![
+            (
+                vec![(2, one), (0, Fr::from(7u64))],
+                vec![(3, one)],
+                vec![(1, one)],
+            ),
+            (vec![], vec![], vec![(4, -one), (2, Fr::from(7u64))]),
+        ];
+        let csr = CsrConstraints::from(constraints.clone());
+
+        assert_eq!(csr.len(), 2);
+        assert_eq!(csr.num_coeffs(), 3);
+        assert_eq!(csr.num_non_zero(), [2, 1, 3]);
+
+        let terms = |c: Constraint<'_, Fr>| {
+            (
+                c.a.iter().collect(),
+                c.b.iter().collect(),
+                c.c.iter().collect(),
+            )
+        };
+        let collected = csr
+            .iter()
+            .map(terms)
+            .collect::<Vec<(Vec<_>, Vec<_>, Vec<_>)>>();
+        assert_eq!(collected, constraints);
+        let collected = csr
+            .par_iter()
+            .map(terms)
+            .collect::<Vec<(Vec<_>, Vec<_>, Vec<_>)>>();
+        assert_eq!(collected, constraints);
+
+        let second = csr.get(1).unwrap();
+        assert!(second.a.is_empty());
+        assert_eq!(second.c.len(), 2);
+        assert!(csr.get(2).is_none());
+    }
+
+    #[test]
+    fn empty() {
+        let csr = CsrConstraints::<Fr>::default();
+        assert!(csr.is_empty());
+        assert_eq!(csr.iter().count(), 0);
+    }
+}
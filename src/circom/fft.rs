@@ -0,0 +1,146 @@
+//! Radix-2 FFTs over a domain whose twiddle factors are computed once
+//!
+//! `ark_poly`'s domains recompute the powers of their root of unity on every transform,
+//! which shows up when the same domain is used for thousands of proofs.
+use ark_ff::PrimeField;
+use rayon::prelude::*;
+
+// below this many butterflies per block, blocks are processed in parallel instead of the
+// butterflies within a block
+const MIN_PARALLEL_BUTTERFLIES: usize = 1 << 10;
+
+/// A multiplicative subgroup of size `2^k` with the powers of its generator
+#[derive(Clone, Debug)]
+pub(crate) struct FftDomain<F> {
+    size: usize,
+    log_size: u32,
+    /// `ω^i` for `i < size / 2`
+    twiddles: Vec<F>,
+    /// `ω^-i` for `i < size / 2`
+    inv_twiddles: Vec<F>,
+}
+
+impl<F: PrimeField> FftDomain<F> {
+    /// The smallest domain with at least `num_coeffs` elements, as chosen by
+    /// `ark_poly::Radix2EvaluationDomain`
+    pub fn new(num_coeffs: usize) -> Option<Self> {
+        let size = num_coeffs.checked_next_power_of_two()?;
+        let omega = F::get_root_of_unity(size)?;
+        let half = size / 2;
+        let twiddles = powers(F::one(), omega, half);
+        let inv_twiddles = powers(F::one(), omega.inverse()?, half);
+        Some(Self {
+            size,
+            log_size: size.trailing_zeros(),
+            twiddles,
+            inv_twiddles,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Evaluates the polynomial with the coefficients `values` over the domain
+    pub fn fft_in_place(&self, values: &mut [F]) {
+        self.transform(values, &self.twiddles);
+    }
+
+    /// Interpolates the evaluations `values` over the domain, without the division by the
+    /// size of the domain
+    pub fn ifft_unscaled_in_place(&self, values: &mut [F]) {
+        self.transform(values, &self.inv_twiddles);
+    }
+
+    fn transform(&self, values: &mut [F], twiddles: &[F]) {
+        assert_eq!(
+            values.len(),
+            self.size,
+            "one value per element of the domain"
+        );
+        if self.size == 1 {
+            return;
+        }
+
+        let shift = usize::BITS - self.log_size;
+        for i in 0..self.size {
+            let j = i.reverse_bits() >> shift;
+            if i < j {
+                values.swap(i, j);
+            }
+        }
+
+        let mut len = 2;
+        while len <= self.size {
+            let half = len / 2;
+            let step = self.size / len;
+            if half < MIN_PARALLEL_BUTTERFLIES {
+                values
+                    .par_chunks_mut(len)
+                    .for_each(|block| butterflies(block, twiddles, step));
+            } else {
+                for block in values.chunks_mut(len) {
+                    let (lo, hi) = block.split_at_mut(half);
+                    lo.par_iter_mut()
+                        .zip(hi)
+                        .enumerate()
+                        .for_each(|(j, (u, v))| butterfly(u, v, twiddles[j * step]));
+                }
+            }
+            len *= 2;
+        }
+    }
+}
+
+fn butterflies<F: PrimeField>(block: &mut [F], twiddles: &[F], step: usize) {
+    let (lo, hi) = block.split_at_mut(block.len() / 2);
+    for (j, (u, v)) in lo.iter_mut().zip(hi).enumerate() {
+        butterfly(u, v, twiddles[j * step]);
+    }
+}
+
+#[inline]
+fn butterfly<F: PrimeField>(u: &mut F, v: &mut F, twiddle: F) {
+    let t = *v * twiddle;
+    *v = *u - t;
+    *u += t;
+}
+
+/// `start·g^i` for `i < len`
+pub(crate) fn powers<F: PrimeField>(start: F, g: F, len: usize) -> Vec<F> {
+    let mut powers = Vec::with_capacity(len);
+    let mut power = start;
+    for _ in 0..len {
+        powers.push(power);
+        power *= g;
+    }
+    powers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+    use ark_std::{test_rng, UniformRand};
+
+    #[test]
+    fn matches_ark_poly() {
+        let mut rng = test_rng();
+        for size in [1, 2, 8, 1 << 12] {
+            let domain = FftDomain::<Fr>::new(size).unwrap();
+            let ark = Radix2EvaluationDomain::<Fr>::new(size).unwrap();
+            assert_eq!(domain.size(), ark.size());
+
+            let coeffs = (0..size).map(|_| Fr::rand(&mut rng)).collect::<Vec<_>>();
+            let mut values = coeffs.clone();
+            domain.fft_in_place(&mut values);
+            assert_eq!(values, ark.fft(&coeffs));
+
+            domain.ifft_unscaled_in_place(&mut values);
+            let size_inv = ark.size_inv;
+            values.iter_mut().for_each(|value| *value *= size_inv);
+            assert_eq!(values, coeffs);
+        }
+    }
+}
@@ -1,7 +1,21 @@
 use ark_ec::PairingEngine;
 
 pub mod r1cs_reader;
-pub use r1cs_reader::{R1CSFile, R1CS};
+pub use r1cs_reader::{ConstraintsIter, R1CSError, R1CSFile, R1CSReader, R1CS};
+
+mod csr;
+pub use csr::{Constraint, CsrConstraints, CsrConstraintsBuilder, LinearCombination};
+
+pub mod sym_reader;
+pub use sym_reader::{SymFile, Symbol};
+
+mod public;
+pub use public::PublicSignals;
+
+mod check;
+pub use check::{
+    check_constraints, CheckError, Term, UnsatisfiedConstraint, UnsatisfiedConstraints,
+};
 
 mod circuit;
 pub use circuit::CircomCircuit;
@@ -12,5 +26,21 @@ pub use builder::{CircomBuilder, CircomConfig};
 mod qap;
 pub use qap::CircomReduction;
 
+mod fft;
+mod msm;
+pub use msm::small_scalar_msm;
+
+mod prepared;
+pub use prepared::PreparedProver;
+
+mod prover;
+pub use prover::CircomProver;
+
+mod batch;
+pub use batch::BatchProver;
+
+mod streaming;
+pub use streaming::StreamingProver;
+
 pub type Constraints<E> = (ConstraintVec<E>, ConstraintVec<E>, ConstraintVec<E>);
 pub type ConstraintVec<E> = Vec<(usize, <E as PairingEngine>::Fr)>;
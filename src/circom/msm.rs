@@ -0,0 +1,361 @@
+//! Multi-scalar multiplications for the Groth16 provers
+//!
+//! Pippenger's algorithm splits the scalars into windows of `c` bits, accumulates the bases
+//! in `2^c` buckets per window, and combines the windows with `c` doublings each. When the
+//! bases are known in advance, storing `2^(c·k)·P` for every window `k` of every base `P`
+//! turns the MSM into a single bucket pass: the bucket reduction is done once instead of
+//! once per window and no doubling is left, at the cost of `bits / c` points per base.
+//!
+//! Otherwise, [`small_scalar_msm`] saves the windows which only hold the zero bits of the
+//! small scalars making most of circom witnesses.
+use ark_ec::{msm::VariableBaseMSM, AffineCurve, ProjectiveCurve};
+use ark_ff::{BigInteger, One, PrimeField, Zero};
+use rayon::prelude::*;
+
+type BigInt<G> = <<G as AffineCurve>::ScalarField as PrimeField>::BigInt;
+
+/// Table of the multiples `2^(c·k)·P` of a list of bases
+#[derive(Clone, Debug)]
+pub(crate) struct FixedBaseMsm<G: AffineCurve> {
+    window: usize,
+    num_windows: usize,
+    /// The multiples of each base, one base after the other
+    table: Vec<G>,
+}
+
+impl<G: AffineCurve> FixedBaseMsm<G> {
+    /// Precomputes the table of `bases`, with a window chosen for `num_threads` threads
+    pub fn new(bases: &[G], num_threads: usize) -> Self {
+        let bits = G::ScalarField::size_in_bits();
+        let window = best_window(bases.len(), num_threads.max(1), bits);
+        let num_windows = bits.div_ceil(window);
+
+        let table = bases
+            .par_iter()
+            .flat_map_iter(|base| {
+                let mut multiple = base.into_projective();
+                (0..num_windows).map(move |_| {
+                    let current = multiple;
+                    for _ in 0..window {
+                        multiple.double_in_place();
+                    }
+                    current
+                })
+            })
+            .collect::<Vec<_>>();
+        let table = table
+            .par_chunks(1 << 12)
+            .flat_map_iter(G::Projective::batch_normalization_into_affine)
+            .collect();
+
+        Self {
+            window,
+            num_windows,
+            table,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len() / self.num_windows
+    }
+
+    /// Computes `Σ scalars[i]·bases[i]`, ignoring the bases without a scalar
+    pub fn msm(&self, scalars: &[BigInt<G>]) -> G::Projective {
+        let len = scalars.len().min(self.len());
+        if len == 0 {
+            return G::Projective::zero();
+        }
+        let chunk_size = len.div_ceil(rayon::current_num_threads());
+
+        self.table[..len * self.num_windows]
+            .par_chunks(chunk_size * self.num_windows)
+            .zip(scalars[..len].par_chunks(chunk_size))
+            .map(|(multiples, scalars)| self.msm_chunk(multiples, scalars))
+            .reduce(G::Projective::zero, |a, b| a + b)
+    }
+
+    fn msm_chunk(&self, multiples: &[G], scalars: &[BigInt<G>]) -> G::Projective {
+        let mut buckets = vec![G::Projective::zero(); (1 << self.window) - 1];
+        for (multiples, scalar) in multiples.chunks(self.num_windows).zip(scalars) {
+            if scalar.is_zero() {
+                continue;
+            }
+            let limbs = scalar.as_ref();
+            for (k, multiple) in multiples.iter().enumerate() {
+                let digit = digit(limbs, k * self.window, self.window);
+                if digit != 0 {
+                    buckets[digit - 1].add_assign_mixed(multiple);
+                }
+            }
+        }
+
+        // Σ (i + 1)·buckets[i] as a sum of running sums
+        let mut sum = G::Projective::zero();
+        let mut running = G::Projective::zero();
+        for bucket in buckets.into_iter().rev() {
+            running += &bucket;
+            sum += &running;
+        }
+        sum
+    }
+}
+
+/// Computes `Σ scalars[i]·bases[i]` as `VariableBaseMSM::multi_scalar_mul` does, with fast
+/// paths for the scalars of circom witnesses, which are mostly bits, selectors and other
+/// small values
+///
+/// Zeros are skipped and the bases of ones are summed directly. The other scalars which fit
+/// in a limb go through Pippenger's algorithm with only as many windows as the largest of
+/// them needs, instead of the `254 / c` windows whose buckets `VariableBaseMSM` reduces even
+/// when all their digits are zero, and only the remaining scalars go through the full MSM.
+///
+/// On the MSMs of the complex circuit of the benches, a witness made of the bit
+/// decompositions of its values, as `Num2Bits` outputs, is about 6 times faster, and a
+/// witness of 16-bit values about 7% faster, as both MSMs add each base in the same two
+/// windows. A witness of large values takes the same time as with `VariableBaseMSM`.
+pub fn small_scalar_msm<G: AffineCurve>(bases: &[G], scalars: &[BigInt<G>]) -> G::Projective {
+    let len = bases.len().min(scalars.len());
+    let (bases, scalars) = (&bases[..len], &scalars[..len]);
+    let one = G::ScalarField::one().into_repr();
+    let is_small = |scalar: &BigInt<G>| scalar.as_ref()[1..].iter().all(|&limb| limb == 0);
+    let num_large = scalars
+        .par_iter()
+        .filter(|scalar| !is_small(scalar))
+        .count();
+    if num_large == len {
+        return VariableBaseMSM::multi_scalar_mul(bases, scalars);
+    }
+
+    let ones = bases
+        .par_iter()
+        .zip(scalars)
+        .filter(|(_, scalar)| **scalar == one)
+        .fold(G::Projective::zero, |mut sum, (base, _)| {
+            sum.add_assign_mixed(base);
+            sum
+        })
+        .reduce(G::Projective::zero, |a, b| a + b);
+
+    // the scalars above one which fit in a limb, with the index of their base
+    let small = scalars
+        .par_iter()
+        .enumerate()
+        .filter(|(_, scalar)| is_small(scalar))
+        .map(|(i, scalar)| (i, scalar.as_ref()[0]))
+        .filter(|&(_, scalar)| scalar > 1)
+        .collect::<Vec<_>>();
+    let small = msm_u64(bases, &small);
+
+    // `VariableBaseMSM` skips zeros, but its window and so the cost of its bucket
+    // reductions grow with the number of scalars, so the large scalars are gathered unless
+    // they are most of them, in which case the small ones are zeroed instead of copying
+    // most of the bases
+    let large = if 2 * num_large < len {
+        let (bases, scalars): (Vec<G>, Vec<BigInt<G>>) = bases
+            .par_iter()
+            .zip(scalars)
+            .filter(|(_, scalar)| !is_small(scalar))
+            .map(|(base, scalar)| (*base, *scalar))
+            .unzip();
+        VariableBaseMSM::multi_scalar_mul(&bases, &scalars)
+    } else {
+        let scalars = scalars
+            .par_iter()
+            .map(|scalar| {
+                if is_small(scalar) {
+                    BigInt::<G>::default()
+                } else {
+                    *scalar
+                }
+            })
+            .collect::<Vec<_>>();
+        VariableBaseMSM::multi_scalar_mul(bases, &scalars)
+    };
+
+    ones + small + large
+}
+
+// Pippenger's algorithm over `(index of the base, scalar)` pairs, with windows up to the
+// highest bit of the scalars. Small scalars only have a few windows, so the scalars are
+// split between the threads rather than the windows.
+fn msm_u64<G: AffineCurve>(bases: &[G], scalars: &[(usize, u64)]) -> G::Projective {
+    let max = scalars.iter().map(|&(_, scalar)| scalar).max().unwrap_or(0);
+    let bits = (u64::BITS - max.leading_zeros()) as usize;
+    if bits == 0 {
+        return G::Projective::zero();
+    }
+    let chunk_size = scalars.len().div_ceil(rayon::current_num_threads());
+    scalars
+        .par_chunks(chunk_size)
+        .map(|chunk| pippenger_u64(bases, chunk, bits))
+        .reduce(G::Projective::zero, |a, b| a + b)
+}
+
+fn pippenger_u64<G: AffineCurve>(
+    bases: &[G],
+    scalars: &[(usize, u64)],
+    bits: usize,
+) -> G::Projective {
+    // the window of `VariableBaseMSM`, which never needs more bits than the scalars have
+    let window = match scalars.len() {
+        len if len < 32 => 3,
+        len => ark_std::log2(len) as usize * 69 / 100 + 2,
+    }
+    .min(bits);
+
+    // from the highest window down, doubling `window` times between two windows
+    let mut total = G::Projective::zero();
+    let mut buckets = vec![G::Projective::zero(); (1 << window) - 1];
+    for start in (0..bits).step_by(window).rev() {
+        for _ in 0..window {
+            total.double_in_place();
+        }
+
+        for &(i, scalar) in scalars {
+            let digit = (scalar >> start) as usize & ((1 << window) - 1);
+            if digit != 0 {
+                buckets[digit - 1].add_assign_mixed(&bases[i]);
+            }
+        }
+        let mut running = G::Projective::zero();
+        for bucket in buckets.iter_mut().rev() {
+            running += &*bucket;
+            total += &running;
+            *bucket = G::Projective::zero();
+        }
+    }
+    total
+}
+
+/// The `window` bits of the scalar with the little-endian `limbs` starting at bit `start`
+fn digit(limbs: &[u64], start: usize, window: usize) -> usize {
+    let (limb, shift) = (start / 64, start % 64);
+    let mut digit = limbs[limb] >> shift;
+    if shift + window > 64 && limb + 1 < limbs.len() {
+        digit |= limbs[limb + 1] << (64 - shift);
+    }
+    (digit & ((1 << window) - 1)) as usize
+}
+
+// the window minimising the additions of a thread: one per base and window, and two per
+// bucket to reduce them
+fn best_window(num_bases: usize, num_threads: usize, bits: usize) -> usize {
+    let per_thread = num_bases.div_ceil(num_threads);
+    (2..=16)
+        .min_by_key(|&window| per_thread * bits.div_ceil(window) + (2 << window))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Fr, G1Affine, G1Projective, G2Affine, G2Projective};
+    use ark_std::{test_rng, UniformRand};
+
+    fn check<G: AffineCurve>(bases: &[G]) {
+        let mut rng = test_rng();
+        let mut scalars = (0..bases.len())
+            .map(|_| G::ScalarField::rand(&mut rng).into_repr())
+            .collect::<Vec<_>>();
+        scalars[0] = G::ScalarField::zero().into_repr();
+        scalars[1] = (-G::ScalarField::one()).into_repr();
+
+        for num_threads in [1, 64] {
+            let table = FixedBaseMsm::new(bases, num_threads);
+            assert_eq!(table.len(), bases.len());
+            assert_eq!(
+                table.msm(&scalars),
+                VariableBaseMSM::multi_scalar_mul(bases, &scalars)
+            );
+            // extra bases are ignored
+            assert_eq!(
+                table.msm(&scalars[..5]),
+                VariableBaseMSM::multi_scalar_mul(&bases[..5], &scalars[..5])
+            );
+        }
+    }
+
+    fn check_small_scalars<G: AffineCurve>(bases: &[G]) {
+        let mut rng = test_rng();
+        let large = |rng: &mut _| G::ScalarField::rand(rng).into_repr();
+        let small = |value: u64| G::ScalarField::from(value).into_repr();
+
+        let mixed = (0..bases.len())
+            .map(|i| match i % 5 {
+                0 => small(0),
+                1 => small(1),
+                2 => small(i as u64),
+                3 => small(u64::MAX - i as u64),
+                _ => large(&mut rng),
+            })
+            .collect::<Vec<_>>();
+        let bits = (0..bases.len())
+            .map(|i| small(i as u64 % 2))
+            .collect::<Vec<_>>();
+        let selectors = (0..bases.len())
+            .map(|i| small(i as u64 * 257 % (1 << 16)))
+            .collect::<Vec<_>>();
+        let mostly_large = (0..bases.len())
+            .map(|i| match i % 5 {
+                0 => small(i as u64),
+                _ => large(&mut rng),
+            })
+            .collect::<Vec<_>>();
+        let dense = (0..bases.len())
+            .map(|_| large(&mut rng))
+            .collect::<Vec<_>>();
+        for scalars in [
+            &mixed,
+            &bits,
+            &selectors,
+            &mostly_large,
+            &dense,
+            &mixed[..7],
+        ] {
+            assert_eq!(
+                small_scalar_msm(bases, scalars),
+                VariableBaseMSM::multi_scalar_mul(bases, scalars)
+            );
+        }
+        assert_eq!(small_scalar_msm(bases, &[small(0)]), G::Projective::zero());
+    }
+
+    #[test]
+    fn small_scalars() {
+        let mut rng = test_rng();
+        let g1 = (0..300)
+            .map(|_| G1Projective::rand(&mut rng).into_affine())
+            .collect::<Vec<G1Affine>>();
+        let g2 = (0..50)
+            .map(|_| G2Projective::rand(&mut rng).into_affine())
+            .collect::<Vec<G2Affine>>();
+        // the small scalars are split between the threads
+        for threads in [1, 4] {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                check_small_scalars(&g1);
+                check_small_scalars(&g2);
+            });
+        }
+    }
+
+    #[test]
+    fn matches_variable_base_msm() {
+        let mut rng = test_rng();
+        let g1 = (0..300)
+            .map(|_| G1Projective::rand(&mut rng).into_affine())
+            .collect::<Vec<G1Affine>>();
+        check(&g1);
+        let g2 = (0..50)
+            .map(|_| G2Projective::rand(&mut rng).into_affine())
+            .collect::<Vec<G2Affine>>();
+        check(&g2);
+        assert_eq!(
+            FixedBaseMsm::<G1Affine>::new(&[], 8).msm(&[Fr::from(2u64).into_repr()]),
+            G1Projective::zero()
+        );
+    }
+}
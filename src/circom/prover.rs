@@ -0,0 +1,235 @@
+//! One-call Groth16 prover for circuits set up with snarkjs
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::{
+    prepare_verifying_key, verify_proof, PreparedVerifyingKey, Proof, ProvingKey, VerifyingKey,
+};
+use ark_relations::r1cs::ConstraintMatrices;
+use ark_std::rand::{thread_rng, Rng};
+use color_eyre::{eyre::eyre, Result};
+use std::{fs::File, io::BufReader, path::Path};
+
+use super::{
+    public::names_from_sym, r1cs_reader::Header, PreparedProver, PublicSignals, R1CSReader, SymFile,
+};
+use crate::{
+    check_circuit,
+    witness::{parse_inputs_json, CircomInputs, InputSignals, WitnessCalculator},
+    Zkey, ZkeyHeader,
+};
+
+/// Proves statements about a circuit with the proving key of its snarkjs zkey and the
+/// witness calculator of its wasm, both loaded once and kept between proofs
+///
+/// Proofs are computed by a [`PreparedProver`], whose fixed-base tables can be enabled with
+/// [`CircomProver::with_fixed_base_tables`].
+///
+/// The prover isn't `Clone`, as a clone of its [`WitnessCalculator`] would share the WASM
+/// instance and its memory, so that two clones computing witnesses on different threads
+/// would corrupt each other's. A [`super::BatchProver`] proves on several threads, with a
+/// new instance of the module for each.
+///
+/// ```rust,ignore
+/// let mut prover =
+///     CircomProver::new("circuit.zkey", "circuit.wasm")?.with_r1cs("circuit.r1cs")?;
+/// let (proof, public_signals) = prover.prove(&inputs)?;
+/// assert!(prover.verify(&proof, &public_signals)?);
+/// ```
+#[derive(Debug)]
+pub struct CircomProver {
+    pub(super) prover: PreparedProver<Bn254>,
+    pub(super) wtns: WitnessCalculator,
+    pub(super) circuit: Circuit,
+}
+
+// what proofs only read, shared with `BatchProver`
+#[derive(Clone, Debug)]
+pub(super) struct Circuit {
+    pvk: PreparedVerifyingKey<Bn254>,
+    header: ZkeyHeader,
+    r1cs: Option<Header>,
+    sym: Option<SymFile>,
+}
+
+impl CircomProver {
+    /// Loads the zkey and the wasm of the circuit
+    ///
+    /// Without the r1cs, see [`CircomProver::with_r1cs`], the circuit's outputs can't be
+    /// told apart from its public inputs and every public signal is returned as a public
+    /// input.
+    pub fn new(zkey: impl AsRef<Path>, wasm: impl AsRef<Path>) -> Result<Self> {
+        let zkey = Zkey::open(zkey)?;
+        let wtns = WitnessCalculator::new(wasm)?;
+        Self::from_parts(zkey.proving_key()?, zkey.matrices()?, *zkey.header(), wtns)
+    }
+
+    pub fn from_parts(
+        pk: ProvingKey<Bn254>,
+        matrices: ConstraintMatrices<Fr>,
+        header: ZkeyHeader,
+        wtns: WitnessCalculator,
+    ) -> Result<Self> {
+        Ok(Self {
+            circuit: Circuit {
+                pvk: prepare_verifying_key(&pk.vk),
+                header,
+                r1cs: None,
+                sym: None,
+            },
+            prover: PreparedProver::new(pk, matrices)?,
+            wtns,
+        })
+    }
+
+    /// Precomputes the fixed-base tables of the proving key, see [`PreparedProver`] for the
+    /// memory they take
+    pub fn with_fixed_base_tables(mut self) -> Self {
+        self.prover = self.prover.with_fixed_base_tables();
+        self
+    }
+
+    /// Reads the header of the circuit's r1cs, and the `.sym` file next to it if there is
+    /// one, to split and name the public signals and to validate the inputs. Fails if the
+    /// zkey or the wasm don't match the r1cs, see [`crate::check_circuit`].
+    pub fn with_r1cs(mut self, r1cs: impl AsRef<Path>) -> Result<Self> {
+        let sym_path = r1cs.as_ref().with_extension("sym");
+        let reader = R1CSReader::<_, Bn254>::new(BufReader::new(File::open(r1cs)?))?;
+        check_circuit(&reader.header, Some(&self.circuit.header), Some(&self.wtns))?;
+        self.circuit.r1cs = Some(reader.header);
+        self.circuit.sym = match File::open(sym_path) {
+            Ok(reader) => Some(SymFile::new(reader)?),
+            Err(_) => None,
+        };
+        Ok(self)
+    }
+
+    pub fn proving_key(&self) -> &ProvingKey<Bn254> {
+        self.prover.proving_key()
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey<Bn254> {
+        &self.proving_key().vk
+    }
+
+    pub fn header(&self) -> &ZkeyHeader {
+        &self.circuit.header
+    }
+
+    /// Returns whether the inputs are validated before computing the witness, which needs
+    /// a runtime reporting the sizes of the input signals or the `.sym` file of the circuit,
+    /// see [`CircomProver::with_r1cs`]
+    pub fn can_validate_inputs(&self) -> bool {
+        self.circuit.can_validate_inputs(&self.wtns)
+    }
+
+    /// Proves the statement for the inputs, e.g. a `HashMap<String, Vec<BigInt>>` or a
+    /// `#[derive(CircomInputs)]` struct, returning the proof and the public signals it
+    /// verifies against. The inputs are validated first if
+    /// [`CircomProver::can_validate_inputs`].
+    pub fn prove(
+        &mut self,
+        inputs: &impl CircomInputs,
+    ) -> Result<(Proof<Bn254>, PublicSignals<Fr>)> {
+        self.prove_with_rng(inputs, &mut thread_rng())
+    }
+
+    /// Proves the statement for inputs in the `input.json` format accepted by snarkjs, see
+    /// [`crate::parse_inputs_json`]
+    pub fn prove_json(&mut self, json: &str) -> Result<(Proof<Bn254>, PublicSignals<Fr>)> {
+        self.prove(&parse_inputs_json(json)?)
+    }
+
+    /// Same as [`CircomProver::prove`], drawing the proof's randomness from `rng`
+    pub fn prove_with_rng<R: Rng>(
+        &mut self,
+        inputs: &impl CircomInputs,
+        rng: &mut R,
+    ) -> Result<(Proof<Bn254>, PublicSignals<Fr>)> {
+        let witness = self.circuit.witness(&mut self.wtns, inputs)?;
+        self.circuit.prove(&mut self.prover, &witness, rng)
+    }
+
+    /// Verifies the proof against the public signals
+    pub fn verify(&self, proof: &Proof<Bn254>, public_signals: &[Fr]) -> Result<bool> {
+        self.circuit.verify(proof, public_signals)
+    }
+}
+
+impl Circuit {
+    pub(super) fn witness(
+        &self,
+        wtns: &mut WitnessCalculator,
+        inputs: &impl CircomInputs,
+    ) -> Result<Vec<Fr>> {
+        let inputs = inputs.to_inputs();
+        self.validate_inputs(
+            wtns,
+            inputs
+                .iter()
+                .map(|(name, values)| (name.as_str(), values.len())),
+        )?;
+
+        let witness = wtns.calculate_witness_element::<Bn254, _>(inputs, false)?;
+        if witness.len() != self.header.n_vars {
+            return Err(eyre!(
+                "the wasm computed {} variables, but the zkey has {}",
+                witness.len(),
+                self.header.n_vars
+            ));
+        }
+        Ok(witness)
+    }
+
+    pub(super) fn prove<R: Rng>(
+        &self,
+        prover: &mut PreparedProver<Bn254>,
+        witness: &[Fr],
+        rng: &mut R,
+    ) -> Result<(Proof<Bn254>, PublicSignals<Fr>)> {
+        let num_inputs = prover.matrices().num_instance_variables;
+        let proof = prover.prove(witness, rng)?;
+
+        Ok((proof, self.public_signals(witness[1..num_inputs].to_vec())))
+    }
+
+    pub(super) fn verify(&self, proof: &Proof<Bn254>, public_signals: &[Fr]) -> Result<bool> {
+        Ok(verify_proof(&self.pvk, proof, public_signals)?)
+    }
+
+    pub(super) fn can_validate_inputs(&self, wtns: &WitnessCalculator) -> bool {
+        wtns.can_validate_inputs() || (self.r1cs.is_some() && self.sym.is_some())
+    }
+
+    // same checks as `CircomBuilder::validate_inputs`, skipped unless `can_validate_inputs`
+    #[cfg_attr(not(feature = "circom-2"), allow(unused_variables))]
+    fn validate_inputs<'a>(
+        &self,
+        wtns: &WitnessCalculator,
+        inputs: impl Iterator<Item = (&'a str, usize)>,
+    ) -> Result<()> {
+        #[cfg(feature = "circom-2")]
+        if wtns.can_validate_inputs() {
+            return wtns.validate_inputs(inputs);
+        }
+
+        if let (Some(r1cs), Some(sym)) = (&self.r1cs, &self.sym) {
+            let num_inputs = (r1cs.n_pub_in + r1cs.n_prv_in) as usize;
+            InputSignals::from_sym(sym, r1cs.n_pub_out as usize, num_inputs).validate(inputs)?;
+        }
+
+        Ok(())
+    }
+
+    fn public_signals(&self, values: Vec<Fr>) -> PublicSignals<Fr> {
+        let num_outputs = self.r1cs.as_ref().map_or(0, |r1cs| r1cs.n_pub_out as usize);
+        let names = self.sym.as_ref().and_then(|sym| {
+            let indices = (1..values.len() + 1).collect::<Vec<_>>();
+            names_from_sym(sym, &indices)
+        });
+
+        let signals = PublicSignals::new(values, num_outputs);
+        match names {
+            Some(names) => signals.with_names(names),
+            None => signals,
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Public signals of a circuit, split into its outputs and public inputs
+use std::ops::Deref;
+
+use super::SymFile;
+
+/// The public signals of a circuit in the order expected by the verifier: the outputs of
+/// the main component followed by its public inputs. Dereferences to the whole slice, so
+/// it can be passed as is to `verify_proof`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublicSignals<F> {
+    values: Vec<F>,
+    num_outputs: usize,
+    names: Option<Vec<String>>,
+}
+
+impl<F> PublicSignals<F> {
+    /// `values` are the outputs followed by the public inputs
+    pub(crate) fn new(values: Vec<F>, num_outputs: usize) -> Self {
+        assert!(
+            num_outputs <= values.len(),
+            "more outputs than public signals"
+        );
+        Self {
+            values,
+            num_outputs,
+            names: None,
+        }
+    }
+
+    /// Names the signals, in the same order as the values
+    pub(crate) fn with_names(mut self, names: Vec<String>) -> Self {
+        assert_eq!(names.len(), self.values.len(), "one name per public signal");
+        self.names = Some(names);
+        self
+    }
+
+    pub fn outputs(&self) -> &[F] {
+        &self.values[..self.num_outputs]
+    }
+
+    pub fn public_inputs(&self) -> &[F] {
+        &self.values[self.num_outputs..]
+    }
+
+    /// The name of every signal, in the same order as the values, if known
+    pub fn names(&self) -> Option<&[String]> {
+        self.names.as_deref()
+    }
+
+    /// Returns the output or public input named `name`. The `main.` prefix may be omitted.
+    pub fn get(&self, name: &str) -> Option<&F> {
+        self.position(name).map(|i| &self.values[i])
+    }
+
+    /// Returns the output named `name`, see [`PublicSignals::get`]
+    pub fn output(&self, name: &str) -> Option<&F> {
+        self.position(name)
+            .filter(|&i| i < self.num_outputs)
+            .map(|i| &self.values[i])
+    }
+
+    /// Returns the public input named `name`, see [`PublicSignals::get`]
+    pub fn public_input(&self, name: &str) -> Option<&F> {
+        self.position(name)
+            .filter(|&i| i >= self.num_outputs)
+            .map(|i| &self.values[i])
+    }
+
+    pub fn into_inner(self) -> Vec<F> {
+        self.values
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.names
+            .as_ref()?
+            .iter()
+            .position(|n| n == name || n.strip_prefix("main.") == Some(name))
+    }
+}
+
+/// Returns the names of the signals stored at `witness_indices`, or `None` if any of them
+/// is missing from the symbol file
+pub(crate) fn names_from_sym(sym: &SymFile, witness_indices: &[usize]) -> Option<Vec<String>> {
+    let min = witness_indices.iter().copied().min()?;
+    let max = witness_indices.iter().copied().max()?;
+    let by_index = sym.names_by_witness_index(min..max + 1);
+
+    witness_indices
+        .iter()
+        .map(|index| by_index.get(index).map(|name| name.to_string()))
+        .collect()
+}
+
+impl<F> Deref for PublicSignals<F> {
+    type Target = [F];
+
+    fn deref(&self) -> &Self::Target {
+        &self.values
+    }
+}
+
+impl<F> AsRef<[F]> for PublicSignals<F> {
+    fn as_ref(&self) -> &[F] {
+        &self.values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named() {
+        let sym = "1,1,0,main.root\n\
+                   2,2,0,main.nullifier\n\
+                   3,3,0,main.recipient\n\
+                   4,4,0,main.secret\n\
+                   5,3,1,main.hasher.in\n";
+        let sym = SymFile::new(sym.as_bytes()).unwrap();
+
+        let names = names_from_sym(&sym, &[1, 2, 3]).unwrap();
+        let signals = PublicSignals::new(vec![10, 20, 30], 2).with_names(names);
+        assert_eq!(signals.outputs(), &[10, 20]);
+        assert_eq!(signals.public_inputs(), &[30]);
+        assert_eq!(&signals[..], &[10, 20, 30]);
+        assert_eq!(signals.get("main.nullifier"), Some(&20));
+        assert_eq!(signals.get("recipient"), Some(&30));
+        assert_eq!(signals.output("root"), Some(&10));
+        assert_eq!(signals.output("recipient"), None);
+        assert_eq!(signals.public_input("recipient"), Some(&30));
+        assert_eq!(signals.public_input("root"), None);
+        assert_eq!(signals.get("secret"), None);
+    }
+
+    #[test]
+    fn unnamed() {
+        let sym = SymFile::new("1,1,0,main.root\n".as_bytes()).unwrap();
+        assert_eq!(names_from_sym(&sym, &[1, 2]), None);
+
+        let signals = PublicSignals::new(vec![10, 20], 1);
+        assert_eq!(signals.names(), None);
+        assert_eq!(signals.get("root"), None);
+        assert_eq!(signals.outputs(), &[10]);
+    }
+}
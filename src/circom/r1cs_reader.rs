@@ -2,22 +2,25 @@
 //! Copied from <https://github.com/poma/zkutil>
 //! Spec: <https://github.com/iden3/r1csfile/blob/master/doc/r1cs_bin_format.md>
 use byteorder::{LittleEndian, ReadBytesExt};
-use std::io::{Error, ErrorKind, Result};
+use std::io::{Error, ErrorKind};
 
 use ark_ec::PairingEngine;
-use ark_ff::FromBytes;
+use ark_ff::{FromBytes, Zero};
+use ark_relations::r1cs::{ConstraintMatrices, Matrix};
 use ark_std::io::{Read, Seek, SeekFrom};
 
-use std::collections::HashMap;
+use std::{collections::HashMap, marker::PhantomData};
 
-use super::{ConstraintVec, Constraints};
+use super::{ConstraintVec, Constraints, CsrConstraints, CsrConstraintsBuilder};
 
 #[derive(Clone, Debug)]
 pub struct R1CS<E: PairingEngine> {
     pub num_inputs: usize,
+    pub num_outputs: usize,
+    pub num_private_inputs: usize,
     pub num_aux: usize,
     pub num_variables: usize,
-    pub constraints: Vec<Constraints<E>>,
+    pub constraints: CsrConstraints<E::Fr>,
     pub wire_mapping: Option<Vec<usize>>,
 }
 
@@ -29,6 +32,8 @@ impl<E: PairingEngine> From<R1CSFile<E>> for R1CS<E> {
         R1CS {
             num_aux,
             num_inputs,
+            num_outputs: file.header.n_pub_out as usize,
+            num_private_inputs: file.header.n_prv_in as usize,
             num_variables,
             constraints: file.constraints,
             wire_mapping: Some(file.wire_mapping.iter().map(|e| *e as usize).collect()),
@@ -36,10 +41,61 @@ impl<E: PairingEngine> From<R1CSFile<E>> for R1CS<E> {
     }
 }
 
+impl<E: PairingEngine> R1CS<E> {
+    /// Returns the A, B and C matrices of the constraints, with one column per wire. This is
+    /// the layout `CircomCircuit::generate_constraints` allocates variables in, so together
+    /// with [`R1CS::permute_witness`] it can be used to prove with
+    /// `create_proof_with_reduction_and_matrices` without synthesizing the circuit.
+    pub fn to_matrices(&self) -> ConstraintMatrices<E::Fr> {
+        let mut a = Vec::with_capacity(self.constraints.len());
+        let mut b = Vec::with_capacity(self.constraints.len());
+        let mut c = Vec::with_capacity(self.constraints.len());
+        for constraint in self.constraints.iter() {
+            a.push(to_row(constraint.a.iter()));
+            b.push(to_row(constraint.b.iter()));
+            c.push(to_row(constraint.c.iter()));
+        }
+
+        ConstraintMatrices {
+            num_instance_variables: self.num_inputs,
+            num_witness_variables: self.num_aux,
+            num_constraints: self.constraints.len(),
+            a_num_non_zero: num_non_zero(&a),
+            b_num_non_zero: num_non_zero(&b),
+            c_num_non_zero: num_non_zero(&c),
+            a,
+            b,
+            c,
+        }
+    }
+
+    /// Reorders a witness computed by the runtime into the wire order of the columns of
+    /// [`R1CS::to_matrices`], following the wire mapping if there is one
+    pub fn permute_witness(&self, witness: &[E::Fr]) -> Vec<E::Fr> {
+        match &self.wire_mapping {
+            Some(m) => m[..self.num_variables]
+                .iter()
+                .map(|i| witness[*i])
+                .collect(),
+            None => witness[..self.num_variables].to_vec(),
+        }
+    }
+}
+
+fn to_row<F: Zero>(lc: impl Iterator<Item = (usize, F)>) -> Vec<(F, usize)> {
+    lc.filter(|(_, coeff)| !coeff.is_zero())
+        .map(|(index, coeff)| (coeff, index))
+        .collect()
+}
+
+fn num_non_zero<F>(matrix: &Matrix<F>) -> usize {
+    matrix.iter().map(|row| row.len()).sum()
+}
+
 pub struct R1CSFile<E: PairingEngine> {
     pub version: u32,
     pub header: Header,
-    pub constraints: Vec<Constraints<E>>,
+    pub constraints: CsrConstraints<E::Fr>,
     pub wire_mapping: Vec<u64>,
 }
 
@@ -49,96 +105,312 @@ impl<E: PairingEngine> R1CSFile<E> {
     /// ```rust,ignore
     /// let reader = BufReader::new(Cursor::new(&data[..]));
     /// ```
-    pub fn new<R: Read + Seek>(mut reader: R) -> Result<R1CSFile<E>> {
+    pub fn new<R: Read + Seek>(reader: R) -> Result<R1CSFile<E>> {
+        let mut reader = R1CSReader::<R, E>::new(reader)?;
+
+        let mut builder = CsrConstraintsBuilder::with_capacity(0);
+        for constraint in reader.constraints()? {
+            let (a, b, c) = constraint?;
+            builder.push(a, b, c);
+        }
+
+        Ok(R1CSFile {
+            version: reader.version,
+            header: reader.header,
+            constraints: builder.build(),
+            wire_mapping: reader.wire_mapping,
+        })
+    }
+}
+
+/// Error returned when reading a malformed R1CS file
+#[derive(thiserror::Error, Debug)]
+pub enum R1CSError {
+    #[error(transparent)]
+    Io(#[from] Error),
+    #[error("invalid magic number")]
+    InvalidMagic,
+    #[error("unsupported version {0}")]
+    UnsupportedVersion(u32),
+    #[error("section {sec_type} of {size} bytes at offset {offset} exceeds the file length of {file_len} bytes")]
+    SectionOutOfBounds {
+        sec_type: u32,
+        offset: u64,
+        size: u64,
+        file_len: u64,
+    },
+    #[error("duplicate {0} section")]
+    DuplicateSection(&'static str),
+    #[error("no {0} section found")]
+    MissingSection(&'static str),
+    #[error("invalid {section} section size: {size} bytes, expected {expected}")]
+    SectionSize {
+        section: &'static str,
+        size: u64,
+        expected: u64,
+    },
+    #[error("unsupported field size {0}, this parser only supports 32-byte fields")]
+    UnsupportedFieldSize(u32),
+    #[error("unsupported prime, this parser only supports bn256")]
+    UnsupportedPrime,
+    #[error(
+        "{n_wires} wires cannot hold the constant wire and {n_signals} input and output signals"
+    )]
+    TooFewWires { n_wires: u32, n_signals: u64 },
+    #[error("constraint {0} exceeds the constraints section")]
+    ConstraintOutOfBounds(u32),
+    #[error("constraint {constraint} references wire {wire} but there are only {n_wires} wires")]
+    WireOutOfRange {
+        constraint: u32,
+        wire: u32,
+        n_wires: u32,
+    },
+    #[error("wire 0 should always be mapped to 0, not {0}")]
+    InvalidConstantWire(u64),
+    #[error("wire {wire} is mapped to label {label} but there are only {n_labels} labels")]
+    LabelOutOfRange {
+        wire: u32,
+        label: u64,
+        n_labels: u64,
+    },
+}
+
+impl From<R1CSError> for Error {
+    fn from(err: R1CSError) -> Self {
+        match err {
+            R1CSError::Io(err) => err,
+            err => Error::new(ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, R1CSError>;
+
+const HEADER_TYPE: u32 = 1;
+const CONSTRAINT_TYPE: u32 = 2;
+const WIRE2LABEL_TYPE: u32 = 3;
+
+fn section_name(sec_type: u32) -> &'static str {
+    match sec_type {
+        HEADER_TYPE => "header",
+        CONSTRAINT_TYPE => "constraints",
+        WIRE2LABEL_TYPE => "wire2label",
+        _ => "unknown",
+    }
+}
+
+/// Streaming R1CS reader. The header and the wire map are parsed eagerly, while the
+/// constraints are only read while iterating over [`R1CSReader::constraints`], so that
+/// circuits larger than the available memory can be processed.
+///
+/// The reader is strict, as r1cs files may come from untrusted sources: every section
+/// must lie within the file and be consumed exactly, and wire indices are checked against
+/// the header. No allocation is larger than the file.
+///
+/// ```rust,ignore
+/// let mut reader = R1CSReader::<_, Bn254>::new(BufReader::new(File::open(path)?))?;
+/// for constraint in reader.constraints()? {
+///     let (a, b, c) = constraint?;
+/// }
+/// ```
+pub struct R1CSReader<R, E: PairingEngine> {
+    pub version: u32,
+    pub header: Header,
+    pub wire_mapping: Vec<u64>,
+    reader: R,
+    constraints_offset: u64,
+    constraints_size: u64,
+    _engine: PhantomData<E>,
+}
+
+impl<R: Read + Seek, E: PairingEngine> R1CSReader<R, E> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let start = reader.seek(SeekFrom::Current(0))?;
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(start))?;
+
         let mut magic = [0u8; 4];
         reader.read_exact(&mut magic)?;
         if magic != [0x72, 0x31, 0x63, 0x73] {
             // magic = "r1cs"
-            return Err(Error::new(ErrorKind::InvalidData, "Invalid magic number"));
+            return Err(R1CSError::InvalidMagic);
         }
 
         let version = reader.read_u32::<LittleEndian>()?;
         if version != 1 {
-            return Err(Error::new(ErrorKind::InvalidData, "Unsupported version"));
+            return Err(R1CSError::UnsupportedVersion(version));
         }
 
         let num_sections = reader.read_u32::<LittleEndian>()?;
 
-        // todo: handle sec_size correctly
-        // section type -> file offset
-        let mut sec_offsets = HashMap::<u32, u64>::new();
-        let mut sec_sizes = HashMap::<u32, u64>::new();
+        // section type -> (file offset, size)
+        let mut sections = HashMap::<u32, (u64, u64)>::new();
 
-        // get file offset of each section
+        // get file offset of each section, the file is read to the end at most once
         for _ in 0..num_sections {
             let sec_type = reader.read_u32::<LittleEndian>()?;
             let sec_size = reader.read_u64::<LittleEndian>()?;
             let offset = reader.seek(SeekFrom::Current(0))?;
-            sec_offsets.insert(sec_type, offset);
-            sec_sizes.insert(sec_type, sec_size);
-            reader.seek(SeekFrom::Current(sec_size as i64))?;
+            if sec_size > file_len.saturating_sub(offset) {
+                return Err(R1CSError::SectionOutOfBounds {
+                    sec_type,
+                    offset,
+                    size: sec_size,
+                    file_len,
+                });
+            }
+            // other sections, e.g. custom gates, are skipped
+            let known = matches!(sec_type, HEADER_TYPE | CONSTRAINT_TYPE | WIRE2LABEL_TYPE);
+            if sections.insert(sec_type, (offset, sec_size)).is_some() && known {
+                return Err(R1CSError::DuplicateSection(section_name(sec_type)));
+            }
+            reader.seek(SeekFrom::Start(offset + sec_size))?;
         }
 
-        let header_type = 1;
-        let constraint_type = 2;
-        let wire2label_type = 3;
+        let section = |sec_type: u32| {
+            sections
+                .get(&sec_type)
+                .copied()
+                .ok_or_else(|| R1CSError::MissingSection(section_name(sec_type)))
+        };
+
+        let (header_offset, header_size) = section(HEADER_TYPE)?;
+        reader.seek(SeekFrom::Start(header_offset))?;
+        let header = Header::new(&mut reader, header_size)?;
+
+        let (constraints_offset, constraints_size) = section(CONSTRAINT_TYPE)?;
+        // constraints vary in size, so the section can only be checked to end with the last
+        // one while reading them, unless there are none
+        if header.n_constraints == 0 && constraints_size != 0 {
+            return Err(R1CSError::SectionSize {
+                section: "constraints",
+                size: constraints_size,
+                expected: 0,
+            });
+        }
 
-        let header_offset = sec_offsets.get(&header_type).ok_or_else(|| {
-            Error::new(
-                ErrorKind::InvalidData,
-                "No section offset for header type found",
-            )
-        });
+        let (wire2label_offset, wire2label_size) = section(WIRE2LABEL_TYPE)?;
+        reader.seek(SeekFrom::Start(wire2label_offset))?;
+        let wire_mapping = read_map(&mut reader, wire2label_size, &header)?;
 
-        reader.seek(SeekFrom::Start(*header_offset?))?;
+        Ok(R1CSReader {
+            version,
+            header,
+            wire_mapping,
+            reader,
+            constraints_offset,
+            constraints_size,
+            _engine: PhantomData,
+        })
+    }
 
-        let header_size = sec_sizes.get(&header_type).ok_or_else(|| {
-            Error::new(
-                ErrorKind::InvalidData,
-                "No section size for header type found",
-            )
-        });
+    /// Returns an iterator reading the constraints one at a time from the start of the
+    /// constraints section. It can be called again for another pass over the file.
+    ///
+    /// Bytes left in the section after the last constraint are reported when reading the
+    /// last constraint, so a pass which stops before it doesn't see them.
+    pub fn constraints(&mut self) -> Result<ConstraintsIter<'_, R, E>> {
+        self.reader.seek(SeekFrom::Start(self.constraints_offset))?;
+        Ok(ConstraintsIter {
+            reader: &mut self.reader,
+            index: 0,
+            n_constraints: self.header.n_constraints,
+            n_wires: self.header.n_wires,
+            section_size: self.constraints_size,
+            remaining_bytes: self.constraints_size,
+            field_size: self.header.field_size as u64,
+            _engine: PhantomData,
+        })
+    }
 
-        let header = Header::new(&mut reader, *header_size?)?;
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
 
-        let constraint_offset = sec_offsets.get(&constraint_type).ok_or_else(|| {
-            Error::new(
-                ErrorKind::InvalidData,
-                "No section offset for constraint type found",
-            )
-        });
+/// Lazily reads the constraints of an R1CS file, see [`R1CSReader::constraints`]
+pub struct ConstraintsIter<'a, R, E> {
+    reader: &'a mut R,
+    // index of the next constraint
+    index: u32,
+    n_constraints: u32,
+    n_wires: u32,
+    section_size: u64,
+    // bytes left in the constraints section, used to bound allocations
+    remaining_bytes: u64,
+    field_size: u64,
+    _engine: PhantomData<E>,
+}
 
-        reader.seek(SeekFrom::Start(*constraint_offset?))?;
+impl<'a, R: Read, E: PairingEngine> ConstraintsIter<'a, R, E> {
+    fn read_constraint_vec(&mut self) -> Result<ConstraintVec<E>> {
+        if self.remaining_bytes < 4 {
+            return Err(R1CSError::ConstraintOutOfBounds(self.index));
+        }
+        let n_vec = self.reader.read_u32::<LittleEndian>()? as u64;
+        let size = 4 + n_vec * (4 + self.field_size);
+        if size > self.remaining_bytes {
+            return Err(R1CSError::ConstraintOutOfBounds(self.index));
+        }
+        self.remaining_bytes -= size;
+
+        let mut vec = Vec::with_capacity(n_vec as usize);
+        for _ in 0..n_vec {
+            let wire = self.reader.read_u32::<LittleEndian>()?;
+            if wire >= self.n_wires {
+                return Err(R1CSError::WireOutOfRange {
+                    constraint: self.index,
+                    wire,
+                    n_wires: self.n_wires,
+                });
+            }
+            vec.push((wire as usize, E::Fr::read(&mut self.reader)?));
+        }
+        Ok(vec)
+    }
 
-        let constraints = read_constraints::<&mut R, E>(&mut reader, &header)?;
+    fn read_constraint(&mut self) -> Result<Constraints<E>> {
+        let constraint = (
+            self.read_constraint_vec()?,
+            self.read_constraint_vec()?,
+            self.read_constraint_vec()?,
+        );
 
-        let wire2label_offset = sec_offsets.get(&wire2label_type).ok_or_else(|| {
-            Error::new(
-                ErrorKind::InvalidData,
-                "No section offset for wire2label type found",
-            )
-        });
+        // the last constraint must end the section
+        if self.index + 1 == self.n_constraints && self.remaining_bytes != 0 {
+            return Err(R1CSError::SectionSize {
+                section: "constraints",
+                size: self.section_size,
+                expected: self.section_size - self.remaining_bytes,
+            });
+        }
+        Ok(constraint)
+    }
+}
 
-        reader.seek(SeekFrom::Start(*wire2label_offset?))?;
+impl<'a, R: Read, E: PairingEngine> Iterator for ConstraintsIter<'a, R, E> {
+    type Item = Result<Constraints<E>>;
 
-        let wire2label_size = sec_sizes.get(&wire2label_type).ok_or_else(|| {
-            Error::new(
-                ErrorKind::InvalidData,
-                "No section size for wire2label type found",
-            )
-        });
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.n_constraints {
+            return None;
+        }
 
-        let wire_mapping = read_map(&mut reader, *wire2label_size?, &header)?;
+        let constraint = self.read_constraint();
+        self.index = match constraint {
+            Ok(_) => self.index + 1,
+            // stop after the first error, the reader's position is lost
+            Err(_) => self.n_constraints,
+        };
+        Some(constraint)
+    }
 
-        Ok(R1CSFile {
-            version,
-            header,
-            constraints,
-            wire_mapping,
-        })
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some((self.n_constraints - self.index) as usize))
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct Header {
     pub field_size: u32,
     pub prime_size: Vec<u8>,
@@ -154,17 +426,17 @@ impl Header {
     fn new<R: Read>(mut reader: R, size: u64) -> Result<Header> {
         let field_size = reader.read_u32::<LittleEndian>()?;
         if field_size != 32 {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                "This parser only supports 32-byte fields",
-            ));
+            return Err(R1CSError::UnsupportedFieldSize(field_size));
         }
 
-        if size != 32 + field_size as u64 {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                "Invalid header section size",
-            ));
+        // the field size, the prime and the counts
+        let expected = 32 + field_size as u64;
+        if size != expected {
+            return Err(R1CSError::SectionSize {
+                section: "header",
+                size,
+                expected,
+            });
         }
 
         let mut prime_size = vec![0u8; field_size as usize];
@@ -174,13 +446,10 @@ impl Header {
             != hex::decode("010000f093f5e1439170b97948e833285d588181b64550b829a031e1724e6430")
                 .unwrap()
         {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                "This parser only supports bn256",
-            ));
+            return Err(R1CSError::UnsupportedPrime);
         }
 
-        Ok(Header {
+        let header = Header {
             field_size,
             prime_size,
             n_wires: reader.read_u32::<LittleEndian>()?,
@@ -189,56 +458,47 @@ impl Header {
             n_prv_in: reader.read_u32::<LittleEndian>()?,
             n_labels: reader.read_u64::<LittleEndian>()?,
             n_constraints: reader.read_u32::<LittleEndian>()?,
-        })
-    }
-}
-
-fn read_constraint_vec<R: Read, E: PairingEngine>(mut reader: R) -> Result<ConstraintVec<E>> {
-    let n_vec = reader.read_u32::<LittleEndian>()? as usize;
-    let mut vec = Vec::with_capacity(n_vec);
-    for _ in 0..n_vec {
-        vec.push((
-            reader.read_u32::<LittleEndian>()? as usize,
-            E::Fr::read(&mut reader)?,
-        ));
-    }
-    Ok(vec)
-}
+        };
+
+        let n_signals = header.n_pub_out as u64 + header.n_pub_in as u64 + header.n_prv_in as u64;
+        if (header.n_wires as u64) < 1 + n_signals {
+            return Err(R1CSError::TooFewWires {
+                n_wires: header.n_wires,
+                n_signals,
+            });
+        }
 
-fn read_constraints<R: Read, E: PairingEngine>(
-    mut reader: R,
-    header: &Header,
-) -> Result<Vec<Constraints<E>>> {
-    // todo check section size
-    let mut vec = Vec::with_capacity(header.n_constraints as usize);
-    for _ in 0..header.n_constraints {
-        vec.push((
-            read_constraint_vec::<&mut R, E>(&mut reader)?,
-            read_constraint_vec::<&mut R, E>(&mut reader)?,
-            read_constraint_vec::<&mut R, E>(&mut reader)?,
-        ));
+        Ok(header)
     }
-    Ok(vec)
 }
 
 fn read_map<R: Read>(mut reader: R, size: u64, header: &Header) -> Result<Vec<u64>> {
-    if size != header.n_wires as u64 * 8 {
-        return Err(Error::new(
-            ErrorKind::InvalidData,
-            "Invalid map section size",
-        ));
+    let expected = header.n_wires as u64 * 8;
+    if size != expected {
+        return Err(R1CSError::SectionSize {
+            section: "wire2label",
+            size,
+            expected,
+        });
     }
+    // the section lies within the file, so this is bounded by the file's length
     let mut vec = Vec::with_capacity(header.n_wires as usize);
-    for _ in 0..header.n_wires {
-        vec.push(reader.read_u64::<LittleEndian>()?);
+    for wire in 0..header.n_wires {
+        let label = reader.read_u64::<LittleEndian>()?;
+        // the witness is indexed by label when it is permuted or checked
+        if label >= header.n_labels {
+            return Err(R1CSError::LabelOutOfRange {
+                wire,
+                label,
+                n_labels: header.n_labels,
+            });
+        }
+        vec.push(label);
     }
-    if vec[0] != 0 {
-        return Err(Error::new(
-            ErrorKind::InvalidData,
-            "Wire 0 should always be mapped to 0",
-        ));
+    match vec.first() {
+        Some(0) | None => Ok(vec),
+        Some(label) => Err(R1CSError::InvalidConstantWire(*label)),
     }
-    Ok(vec)
 }
 
 #[cfg(test)]
@@ -247,9 +507,8 @@ mod tests {
     use ark_bn254::{Bn254, Fr};
     use ark_std::io::{BufReader, Cursor};
 
-    #[test]
-    fn sample() {
-        let data = hex_literal::hex!(
+    fn sample_data() -> Vec<u8> {
+        hex_literal::hex!(
             "
         72316373
         01000000
@@ -299,8 +558,13 @@ mod tests {
         0f000000 00000000
         44010000 00000000
     "
-        );
+        )
+        .to_vec()
+    }
 
+    #[test]
+    fn sample() {
+        let data = sample_data();
         let reader = BufReader::new(Cursor::new(&data[..]));
         let file = R1CSFile::<Bn254>::new(reader).unwrap();
         assert_eq!(file.version, 1);
@@ -319,14 +583,199 @@ mod tests {
         assert_eq!(file.header.n_constraints, 3);
 
         assert_eq!(file.constraints.len(), 3);
-        assert_eq!(file.constraints[0].0.len(), 2);
-        assert_eq!(file.constraints[0].0[0].0, 5);
-        assert_eq!(file.constraints[0].0[0].1, Fr::from(3));
-        assert_eq!(file.constraints[2].1[0].0, 0);
-        assert_eq!(file.constraints[2].1[0].1, Fr::from(6));
-        assert_eq!(file.constraints[1].2.len(), 0);
+        let constraints = file.constraints.iter().collect::<Vec<_>>();
+        assert_eq!(constraints[0].a.len(), 2);
+        assert_eq!(constraints[0].a.iter().next(), Some((5, Fr::from(3))));
+        assert_eq!(constraints[2].b.iter().next(), Some((0, Fr::from(6))));
+        assert_eq!(constraints[1].c.len(), 0);
 
         assert_eq!(file.wire_mapping.len(), 7);
         assert_eq!(file.wire_mapping[1], 3);
     }
+
+    #[test]
+    fn streaming() {
+        let data = sample_data();
+        let mut reader = R1CSReader::<_, Bn254>::new(Cursor::new(&data[..])).unwrap();
+        assert_eq!(reader.header.n_constraints, 3);
+        assert_eq!(reader.wire_mapping.len(), 7);
+
+        let constraints = reader
+            .constraints()
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(constraints.len(), 3);
+        assert_eq!(constraints[0].0[0], (5, Fr::from(3)));
+
+        // a second pass reads the same constraints
+        let first = reader.constraints().unwrap().next().unwrap().unwrap();
+        assert_eq!(first, constraints[0]);
+    }
+
+    #[test]
+    fn streaming_huge_counts() {
+        let mut data = sample_data();
+        // claim u32::MAX terms in the first linear combination
+        let pos = data
+            .windows(8)
+            .position(|w| w == [0x02, 0, 0, 0, 0x05, 0, 0, 0])
+            .unwrap();
+        data[pos..pos + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let mut reader = R1CSReader::<_, Bn254>::new(Cursor::new(&data[..])).unwrap();
+        let mut constraints = reader.constraints().unwrap();
+        let err = constraints.next().unwrap().unwrap_err();
+        assert!(matches!(err, R1CSError::ConstraintOutOfBounds(0)));
+        assert!(constraints.next().is_none());
+    }
+
+    // offsets into `sample_data`
+    const N_WIRES: usize = 60;
+    const N_CONSTRAINTS: usize = 84;
+    const FIRST_WIRE: usize = 104;
+
+    fn read_err(data: &[u8]) -> R1CSError {
+        R1CSFile::<Bn254>::new(Cursor::new(data)).err().unwrap()
+    }
+
+    #[test]
+    fn truncated() {
+        let data = sample_data();
+        let err = read_err(&data[..data.len() - 1]);
+        assert!(matches!(
+            err,
+            R1CSError::SectionOutOfBounds {
+                sec_type: 3,
+                size: 56,
+                ..
+            }
+        ));
+
+        let err: Error = read_err(&data[..10]).into();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn unconsumed_constraints_section() {
+        let mut data = sample_data();
+        data[N_CONSTRAINTS] = 2;
+        let err = read_err(&data);
+        assert!(matches!(
+            err,
+            R1CSError::SectionSize {
+                section: "constraints",
+                size: 648,
+                ..
+            }
+        ));
+
+        let err: Error = err.into();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // without constraints, the section is checked before reading them
+        data[N_CONSTRAINTS] = 0;
+        let err = R1CSReader::<_, Bn254>::new(Cursor::new(&data[..]))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            R1CSError::SectionSize {
+                section: "constraints",
+                size: 648,
+                expected: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn wire_out_of_range() {
+        let mut data = sample_data();
+        data[FIRST_WIRE] = 7;
+        let err = read_err(&data);
+        assert!(matches!(
+            err,
+            R1CSError::WireOutOfRange {
+                constraint: 0,
+                wire: 7,
+                n_wires: 7
+            }
+        ));
+    }
+
+    #[test]
+    fn label_out_of_range() {
+        let mut data = sample_data();
+        let last_label = data.len() - 8;
+        data[last_label..].copy_from_slice(&0x03e8u64.to_le_bytes());
+        let err = read_err(&data);
+        assert!(matches!(
+            err,
+            R1CSError::LabelOutOfRange {
+                wire: 6,
+                label: 0x03e8,
+                n_labels: 0x03e8
+            }
+        ));
+    }
+
+    #[test]
+    fn too_few_wires() {
+        let mut data = sample_data();
+        data[N_WIRES] = 0;
+        let err = read_err(&data);
+        assert!(matches!(
+            err,
+            R1CSError::TooFewWires {
+                n_wires: 0,
+                n_signals: 6
+            }
+        ));
+    }
+
+    #[test]
+    fn matrices() {
+        let r1cs = R1CS::<Bn254> {
+            num_inputs: 2,
+            num_outputs: 1,
+            num_private_inputs: 2,
+            num_aux: 2,
+            num_variables: 4,
+            constraints: vec![(
+                vec![(2, Fr::from(2)), (0, Fr::from(0))],
+                vec![(3, Fr::from(1))],
+                vec![(1, Fr::from(1)), (0, Fr::from(5))],
+            )]
+            .into(),
+            wire_mapping: Some(vec![0, 3, 1, 2]),
+        };
+
+        let matrices = r1cs.to_matrices();
+        assert_eq!(matrices.num_instance_variables, 2);
+        assert_eq!(matrices.num_witness_variables, 2);
+        assert_eq!(matrices.num_constraints, 1);
+        assert_eq!(matrices.a, vec![vec![(Fr::from(2), 2)]]);
+        assert_eq!(matrices.b, vec![vec![(Fr::from(1), 3)]]);
+        assert_eq!(matrices.c, vec![vec![(Fr::from(1), 1), (Fr::from(5), 0)]]);
+        assert_eq!(
+            (
+                matrices.a_num_non_zero,
+                matrices.b_num_non_zero,
+                matrices.c_num_non_zero
+            ),
+            (1, 1, 2)
+        );
+
+        let witness = [1u64, 10, 20, 30]
+            .iter()
+            .map(|v| Fr::from(*v))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            r1cs.permute_witness(&witness),
+            [1u64, 30, 10, 20]
+                .iter()
+                .map(|v| Fr::from(*v))
+                .collect::<Vec<_>>()
+        );
+    }
 }
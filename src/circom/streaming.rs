@@ -0,0 +1,334 @@
+//! Groth16 proving with the proving key left on disk
+use ark_bn254::{Bn254, Fr, G1Projective};
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{FftField, Field, PrimeField, UniformRand, Zero};
+use ark_groth16::Proof;
+use ark_relations::r1cs::SynthesisError;
+use ark_std::rand::Rng;
+use color_eyre::Result;
+use memmap2::Mmap;
+use rayon::prelude::*;
+use std::{ops::Range, path::Path};
+
+use super::{fft::FftDomain, msm::small_scalar_msm};
+use crate::{Zkey, ZkeyError};
+
+// 64 MiB of G1 points
+const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+// number of coset powers computed by each rayon task
+const COSET_CHUNK_SIZE: usize = 1 << 12;
+
+/// A Groth16 prover for circuits whose proving key doesn't fit in memory, computing the same
+/// proofs as `create_proof_with_reduction_and_matrices::<_, CircomReduction>`
+///
+/// The queries are decoded from the memory-mapped zkey one chunk of points at a time while
+/// the MSMs run, so that the OS can evict the pages of the key once they are used, and the
+/// witness map is computed from the coefficients of the zkey without decoding its matrices.
+/// For a domain of size `n`, a proof takes, besides the witness held by the caller:
+/// - four vectors of `n` field elements during the witness map, for the evaluations of A, B
+///   and C and the twiddle factors of the FFTs, and a chunk of coefficients;
+/// - a vector of `n` field elements for H during its MSM, and a chunk of points and of
+///   scalars during each MSM, see [`StreamingProver::with_chunk_size`].
+///
+/// A circuit with `2^26` constraints and as many variables has a domain of `2^27` elements,
+/// as snarkjs adds a constraint for each public input. Its witness map takes 16 GiB, about
+/// 18 GiB with its 2 GiB witness, while the queries of its zkey take 28 GiB.
+///
+/// Decoding the key at every proof makes proofs slower than with a [`super::PreparedProver`],
+/// so this is meant for the circuits which can't be proven otherwise.
+///
+/// ```rust,ignore
+/// let prover = StreamingProver::open("circuit.zkey")?;
+/// let witness = wtns.calculate_witness_element::<Bn254, _>(inputs, false)?;
+/// let proof = prover.prove(&witness, &mut rng)?;
+/// ```
+#[derive(Debug)]
+pub struct StreamingProver<B = Mmap> {
+    zkey: Zkey<B>,
+    chunk_size: usize,
+}
+
+impl StreamingProver<Mmap> {
+    /// Memory-maps the zkey at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ZkeyError> {
+        Ok(Self::new(Zkey::open(path)?))
+    }
+}
+
+impl<B: AsRef<[u8]>> StreamingProver<B> {
+    /// Proves with the key of `zkey`, whose points are validated as set by
+    /// [`Zkey::with_validation`] as they are decoded
+    pub fn new(zkey: Zkey<B>) -> Self {
+        Self {
+            zkey,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    /// Sets the number of points, or of coefficients of the matrices, decoded at once,
+    /// `2^20` by default. Panics if it is zero.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunks can't be empty");
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    pub fn zkey(&self) -> &Zkey<B> {
+        &self.zkey
+    }
+
+    /// Proves the statement with the witness `full_assignment`, as computed by
+    /// [`crate::WitnessCalculator::calculate_witness_element`]
+    pub fn prove<R: Rng>(&self, full_assignment: &[Fr], rng: &mut R) -> Result<Proof<Bn254>> {
+        let r = Fr::rand(rng);
+        let s = Fr::rand(rng);
+        self.create_proof(r, s, full_assignment)
+    }
+
+    /// Proves the statement with the randomness `r` and `s`. Fails with
+    /// `AssignmentMissing` if the witness doesn't have one value per variable, or with the
+    /// [`ZkeyError`] of the first chunk of the key which can't be decoded.
+    pub fn create_proof(&self, r: Fr, s: Fr, full_assignment: &[Fr]) -> Result<Proof<Bn254>> {
+        let zkey = &self.zkey;
+        if full_assignment.len() != zkey.n_vars() {
+            return Err(SynthesisError::AssignmentMissing.into());
+        }
+        let pk = zkey.proving_key_header()?;
+
+        // H is the largest vector, so it is dropped before the other MSMs
+        let h = self.witness_map(full_assignment)?;
+        let h_acc = self.msm(&h, |range| zkey.h_query_range(range))?;
+        drop(h);
+
+        let aux_assignment = &full_assignment[zkey.n_public() + 1..];
+        let l_aux_acc = self.msm(aux_assignment, |range| zkey.l_query_range(range))?;
+        // the MSMs include the first point of the queries, as `full_assignment[0]` is 1
+        let a_acc = self.msm(full_assignment, |range| zkey.a_query_range(range))?;
+        let b_g1_acc = if r.is_zero() {
+            G1Projective::zero()
+        } else {
+            self.msm(full_assignment, |range| zkey.b_g1_query_range(range))?
+        };
+        let b_g2_acc = self.msm(full_assignment, |range| zkey.b_g2_query_range(range))?;
+
+        // same combination as `ark_groth16::create_proof_with_assignment`
+        let r_s_delta_g1 = pk
+            .delta_g1
+            .into_projective()
+            .mul(r.into_repr())
+            .mul(s.into_repr());
+        let mut g_a = pk.delta_g1.mul(r) + a_acc;
+        g_a.add_assign_mixed(&pk.vk.alpha_g1);
+        let g1_b = if r.is_zero() {
+            G1Projective::zero()
+        } else {
+            let mut g1_b = pk.delta_g1.mul(s) + b_g1_acc;
+            g1_b.add_assign_mixed(&pk.beta_g1);
+            g1_b
+        };
+        let mut g2_b = pk.vk.delta_g2.mul(s) + b_g2_acc;
+        g2_b.add_assign_mixed(&pk.vk.beta_g2);
+
+        let mut g_c = g_a.mul(s.into_repr());
+        g_c += &g1_b.mul(r.into_repr());
+        g_c -= &r_s_delta_g1;
+        g_c += &l_aux_acc;
+        g_c += &h_acc;
+
+        Ok(Proof {
+            a: g_a.into_affine(),
+            b: g2_b.into_affine(),
+            c: g_c.into_affine(),
+        })
+    }
+
+    /// Computes the coefficients of H as `CircomReduction` does, evaluating A and B from the
+    /// coefficients of the zkey, which also hold the constraints of the public inputs
+    fn witness_map(&self, full_assignment: &[Fr]) -> Result<Vec<Fr>> {
+        let n = self.zkey.domain_size();
+        let domain = FftDomain::new(n)
+            .filter(|domain| domain.size() == n)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+
+        let mut a = vec![Fr::zero(); n];
+        let mut b = vec![Fr::zero(); n];
+        // each thread accumulates the coefficients of its own block of constraints
+        let block_size = n.div_ceil(rayon::current_num_threads());
+        let num_coeffs = self.zkey.num_coefficients()?;
+        for range in chunks(num_coeffs, self.chunk_size) {
+            let mut coeffs = self.zkey.coefficients(range)?;
+            // snarkjs writes the coefficients in constraint order, so this only checks it
+            coeffs.par_sort_by_key(|&(_, constraint, _, _)| constraint);
+            a.par_chunks_mut(block_size)
+                .zip(b.par_chunks_mut(block_size))
+                .enumerate()
+                .for_each(|(block, (a, b))| {
+                    let start = block * block_size;
+                    let first = coeffs.partition_point(|c| (c.1 as usize) < start);
+                    let last = coeffs.partition_point(|c| (c.1 as usize) < start + a.len());
+                    for &(matrix, constraint, signal, value) in &coeffs[first..last] {
+                        let evals = if matrix == 0 { &mut *a } else { &mut *b };
+                        evals[constraint as usize - start] +=
+                            value * full_assignment[signal as usize];
+                    }
+                });
+        }
+        // B is zero on the constraints of the public inputs, and so is C
+        let mut c = a
+            .par_iter()
+            .zip(&b)
+            .map(|(a, b)| *a * b)
+            .collect::<Vec<_>>();
+
+        // evaluations over the coset of the domain
+        let g = Fr::get_root_of_unity(2 * n).ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        let n_inv = Fr::from(n as u64)
+            .inverse()
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        for evals in [&mut a, &mut b, &mut c] {
+            domain.ifft_unscaled_in_place(evals);
+            // multiplies by `g^i / n`, without storing the powers
+            evals
+                .par_chunks_mut(COSET_CHUNK_SIZE)
+                .enumerate()
+                .for_each(|(i, chunk)| {
+                    let mut power = n_inv * g.pow([(i * COSET_CHUNK_SIZE) as u64]);
+                    for value in chunk {
+                        *value *= power;
+                        power *= g;
+                    }
+                });
+            domain.fft_in_place(evals);
+        }
+
+        a.par_iter_mut()
+            .zip(b)
+            .zip(c)
+            .for_each(|((a, b), c)| *a = *a * b - c);
+        Ok(a)
+    }
+
+    /// `Σ scalars[i]·query[i]`, decoding the query a chunk at a time with `query_range`
+    fn msm<G: AffineCurve<ScalarField = Fr>>(
+        &self,
+        scalars: &[Fr],
+        query_range: impl Fn(Range<usize>) -> Result<Vec<G>, ZkeyError>,
+    ) -> Result<G::Projective, ZkeyError> {
+        let mut acc = G::Projective::zero();
+        for range in chunks(scalars.len(), self.chunk_size) {
+            let bigints = scalars[range.clone()]
+                .par_iter()
+                .map(|scalar| scalar.into_repr())
+                .collect::<Vec<_>>();
+            acc += small_scalar_msm(&query_range(range)?, &bigints);
+        }
+        Ok(acc)
+    }
+}
+
+// splits `0..len` into ranges of `chunk_size`
+fn chunks(len: usize, chunk_size: usize) -> impl Iterator<Item = Range<usize>> {
+    (0..len)
+        .step_by(chunk_size)
+        .map(move |start| start..len.min(start + chunk_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_zkey, CircomReduction, WitnessCalculator};
+    use ark_ff::One;
+    use ark_groth16::create_proof_with_reduction_and_matrices;
+    use ark_std::test_rng;
+    use num_bigint::BigInt;
+    use std::fs::File;
+
+    const PATH: &str = "./test-vectors/test.zkey";
+
+    #[test]
+    fn matches_ark_groth16() {
+        let (pk, matrices) = read_zkey(&mut File::open(PATH).unwrap()).unwrap();
+        let mut wtns = WitnessCalculator::new("./test-vectors/mycircuit.wasm").unwrap();
+        let inputs = vec![
+            ("a".to_string(), vec![BigInt::from(3)]),
+            ("b".to_string(), vec![BigInt::from(11)]),
+        ];
+        let witness = wtns
+            .calculate_witness_element::<Bn254, _>(inputs, false)
+            .unwrap();
+
+        let mut rng = test_rng();
+        let (r, s) = (Fr::rand(&mut rng), Fr::rand(&mut rng));
+        let expected = create_proof_with_reduction_and_matrices::<_, CircomReduction>(
+            &pk,
+            r,
+            s,
+            &matrices,
+            matrices.num_instance_variables,
+            matrices.num_constraints,
+            &witness,
+        )
+        .unwrap();
+
+        // with several threads, the constraints are split in blocks for the witness map
+        for threads in [1, 3] {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            for chunk_size in [1, 3, DEFAULT_CHUNK_SIZE] {
+                let prover = StreamingProver::open(PATH)
+                    .unwrap()
+                    .with_chunk_size(chunk_size);
+                let proof = pool.install(|| prover.create_proof(r, s, &witness).unwrap());
+                assert_eq!(proof, expected);
+            }
+        }
+
+        let prover = StreamingProver::open(PATH).unwrap();
+        let err = prover
+            .create_proof(r, s, &witness[..3])
+            .unwrap_err()
+            .downcast::<SynthesisError>()
+            .unwrap();
+        assert!(matches!(err, SynthesisError::AssignmentMissing));
+    }
+
+    // the position of the data of the section `id`, after the magic, the version and the
+    // number of sections, with each section preceded by its id and its size
+    fn section_start(data: &[u8], id: u32) -> usize {
+        let mut position = 12;
+        loop {
+            let header = &data[position..position + 12];
+            position += 12;
+            if header[..4] == id.to_le_bytes() {
+                return position;
+            }
+            let mut size = [0; 8];
+            size.copy_from_slice(&header[4..]);
+            position += u64::from_le_bytes(size) as usize;
+        }
+    }
+
+    #[test]
+    fn invalid_chunks() {
+        let mut data = std::fs::read(PATH).unwrap();
+        // x coordinate of the last point of the A query
+        let position = section_start(&data, 5) + 3 * 64;
+        data[position] ^= 1;
+        let prover = StreamingProver::new(Zkey::from_bytes(data).unwrap()).with_chunk_size(2);
+
+        let err = prover
+            .prove(&[Fr::one(); 4], &mut test_rng())
+            .unwrap_err()
+            .downcast::<ZkeyError>()
+            .unwrap();
+        assert!(matches!(
+            err,
+            ZkeyError::InvalidPoint {
+                section: 5,
+                source: crate::InvalidPoint::NotOnCurve { index: 3 }
+            }
+        ));
+    }
+}
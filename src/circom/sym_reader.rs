@@ -0,0 +1,117 @@
+//! Circom symbol file reader
+//!
+//! Each line of a `.sym` file describes one signal of the circuit as
+//! `label,witness_index,component,name`, where `witness_index` is `-1` for signals which
+//! were optimized out of the witness.
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Error, ErrorKind, Read, Result},
+    ops::Range,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub label: usize,
+    pub witness_index: Option<usize>,
+    pub component: usize,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SymFile {
+    pub symbols: Vec<Symbol>,
+}
+
+impl SymFile {
+    pub fn new<R: Read>(reader: R) -> Result<SymFile> {
+        let mut symbols = Vec::new();
+        for (i, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            symbols.push(parse_symbol(line).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Invalid symbol on line {}: {}", i + 1, line),
+                )
+            })?);
+        }
+
+        Ok(SymFile { symbols })
+    }
+
+    /// Returns the symbol with the provided fully qualified name, e.g. `main.a`
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|s| s.name == name)
+    }
+
+    /// Returns the name of the signal stored at each witness index in `range`. When
+    /// several signals share an index, e.g. a sub-component input wired to a main input,
+    /// the one closest to the main component is kept.
+    pub fn names_by_witness_index(&self, range: Range<usize>) -> HashMap<usize, &str> {
+        let mut by_index: HashMap<usize, (usize, &str)> = HashMap::new();
+        for symbol in &self.symbols {
+            let index = match symbol.witness_index {
+                Some(index) if range.contains(&index) => index,
+                _ => continue,
+            };
+            let candidate = (symbol.component, symbol.name.as_str());
+            by_index
+                .entry(index)
+                .and_modify(|current| {
+                    if (candidate.0, candidate.1.len()) < (current.0, current.1.len()) {
+                        *current = candidate;
+                    }
+                })
+                .or_insert(candidate);
+        }
+
+        by_index
+            .into_iter()
+            .map(|(index, (_, name))| (index, name))
+            .collect()
+    }
+}
+
+fn parse_symbol(line: &str) -> Option<Symbol> {
+    let mut parts = line.splitn(4, ',');
+    let label = parts.next()?.trim().parse().ok()?;
+    let witness_index: i64 = parts.next()?.trim().parse().ok()?;
+    let component = parts.next()?.trim().parse().ok()?;
+    let name = parts.next()?.trim().to_string();
+
+    Some(Symbol {
+        label,
+        witness_index: if witness_index < 0 {
+            None
+        } else {
+            Some(witness_index as usize)
+        },
+        component,
+        name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample() {
+        let data = "1,2,0,main.a\n2,3,0,main.b\n3,-1,1,main.sub.x[0]\n";
+        let file = SymFile::new(data.as_bytes()).unwrap();
+        assert_eq!(file.symbols.len(), 3);
+        assert_eq!(file.get("main.b").unwrap().witness_index, Some(3));
+        assert_eq!(file.symbols[2].witness_index, None);
+        assert_eq!(file.symbols[2].component, 1);
+        assert_eq!(file.symbols[2].name, "main.sub.x[0]");
+    }
+
+    #[test]
+    fn invalid_line() {
+        let err = SymFile::new("1,2,main.a\n".as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
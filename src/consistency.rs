@@ -0,0 +1,275 @@
+//! Consistency checks between the artifacts of a circuit
+//!
+//! A zkey or a wasm from another version of the circuit than the r1cs loads fine, but produces
+//! witnesses that don't satisfy the constraints or proofs that don't verify. The r1cs header
+//! is used as the reference every other artifact is compared to.
+//!
+//! The circuit hash stored in a zkey can't be recomputed from the r1cs, see
+//! [`check_circuit_hash`].
+use color_eyre::eyre::eyre;
+use std::fmt;
+
+use num_bigint::{BigInt, Sign};
+
+#[cfg(feature = "circom-2")]
+use crate::witness::Circom2;
+use crate::{
+    circom::r1cs_reader::Header,
+    witness::CircomBase,
+    zkey::{CircuitHash, ZkeyHeader},
+    WitnessCalculator,
+};
+
+/// An artifact compared to the r1cs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Artifact {
+    Zkey,
+    Wasm,
+}
+
+impl fmt::Display for Artifact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Artifact::Zkey => write!(f, "zkey"),
+            Artifact::Wasm => write!(f, "wasm"),
+        }
+    }
+}
+
+/// A property of an artifact which doesn't match the circuit
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    #[error("the {artifact} has {found} variables, but the r1cs has {expected}")]
+    NVars {
+        artifact: Artifact,
+        expected: usize,
+        found: usize,
+    },
+    #[error("the zkey has {found} public signals, but the r1cs has {expected}")]
+    NPublic { expected: usize, found: usize },
+    #[error("the zkey's domain of size {domain_size} is smaller than the {required} constraints of the r1cs")]
+    DomainSize { domain_size: usize, required: usize },
+    #[error("the wasm computes modulo {found}, but the r1cs uses the prime {expected}")]
+    Prime { expected: BigInt, found: BigInt },
+    #[error(
+        "the zkey was set up for the circuit hash {}, not the expected one",
+        hex::encode(.0)
+    )]
+    CircuitHash(CircuitHash),
+}
+
+/// Error returned by [`check_circuit`]
+#[derive(thiserror::Error, Debug)]
+pub enum ConsistencyError {
+    #[error("could not query the wasm: {0}")]
+    Wasm(color_eyre::Report),
+    #[error("{}", fmt_mismatches(.0))]
+    Mismatches(Vec<Mismatch>),
+}
+
+fn fmt_mismatches(mismatches: &[Mismatch]) -> String {
+    let mismatches = mismatches
+        .iter()
+        .map(|mismatch| mismatch.to_string())
+        .collect::<Vec<_>>();
+    format!("inconsistent circuit: {}", mismatches.join(", "))
+}
+
+/// Checks that the zkey and the wasm, if provided, were generated from the circuit whose r1cs
+/// has the header `r1cs`, returning every mismatch found
+///
+/// ```rust,ignore
+/// let r1cs = R1CSFile::<Bn254>::new(File::open("circuit.r1cs")?)?;
+/// let zkey = read_zkey_header(&mut File::open("circuit.zkey")?)?;
+/// let wasm = WitnessCalculator::new("circuit.wasm")?;
+/// check_circuit(&r1cs.header, Some(&zkey), Some(&wasm))?;
+/// ```
+pub fn check_circuit(
+    r1cs: &Header,
+    zkey: Option<&ZkeyHeader>,
+    wasm: Option<&WitnessCalculator>,
+) -> Result<(), ConsistencyError> {
+    let n_vars = r1cs.n_wires as usize;
+    let n_public = (r1cs.n_pub_out + r1cs.n_pub_in) as usize;
+    let mut mismatches = vec![];
+
+    if let Some(zkey) = zkey {
+        if zkey.n_vars != n_vars {
+            mismatches.push(Mismatch::NVars {
+                artifact: Artifact::Zkey,
+                expected: n_vars,
+                found: zkey.n_vars,
+            });
+        }
+        if zkey.n_public != n_public {
+            mismatches.push(Mismatch::NPublic {
+                expected: n_public,
+                found: zkey.n_public,
+            });
+        }
+        // snarkjs adds a constraint `x·0 = 0` for the constant and each public signal
+        let required = r1cs.n_constraints as usize + n_public + 1;
+        if zkey.domain_size < required {
+            mismatches.push(Mismatch::DomainSize {
+                domain_size: zkey.domain_size,
+                required,
+            });
+        }
+    }
+
+    if let Some(wasm) = wasm {
+        let prime = BigInt::from_bytes_le(Sign::Plus, &r1cs.prime_size);
+        if wasm.memory.prime != prime {
+            mismatches.push(Mismatch::Prime {
+                expected: prime,
+                found: wasm.memory.prime.clone(),
+            });
+        }
+        let wasm_n_vars = wasm_n_vars(wasm).map_err(ConsistencyError::Wasm)?;
+        if wasm_n_vars != n_vars {
+            mismatches.push(Mismatch::NVars {
+                artifact: Artifact::Wasm,
+                expected: n_vars,
+                found: wasm_n_vars,
+            });
+        }
+    }
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(ConsistencyError::Mismatches(mismatches))
+    }
+}
+
+// the number of signals of the witness computed by the wasm, which circom 2 calls its size
+fn wasm_n_vars(wasm: &WitnessCalculator) -> color_eyre::Result<usize> {
+    let name = match wasm.circom_version {
+        2 => "getWitnessSize",
+        _ => "getNVars",
+    };
+    if !wasm.instance.has_function(name) {
+        return Err(eyre!("the wasm doesn't export `{}`", name));
+    }
+
+    #[cfg(feature = "circom-2")]
+    if wasm.circom_version == 2 {
+        return Ok(wasm.instance.get_witness_size()? as usize);
+    }
+    Ok(wasm.instance.get_n_vars()? as usize)
+}
+
+/// Checks that the zkey was set up for the circuit with the hash `expected`
+///
+/// The hash can't be computed from the r1cs: snarkjs hashes the initial contributions to
+/// the setup, which are derived from the powers of tau as well as from the constraints. The
+/// expected hash is the one printed by snarkjs when the zkey was generated, or the one of a
+/// zkey known to match the circuit.
+pub fn check_circuit_hash(zkey: &ZkeyHeader, expected: &CircuitHash) -> Result<(), Mismatch> {
+    if &zkey.circuit_hash != expected {
+        return Err(Mismatch::CircuitHash(zkey.circuit_hash));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{circom::R1CSFile, read_zkey_header};
+    use ark_bn254::Bn254;
+    use std::fs::File;
+
+    fn artifacts() -> (Header, ZkeyHeader, WitnessCalculator) {
+        let r1cs = R1CSFile::<Bn254>::new(File::open("./test-vectors/mycircuit.r1cs").unwrap())
+            .unwrap()
+            .header;
+        let zkey = read_zkey_header(&mut File::open("./test-vectors/test.zkey").unwrap()).unwrap();
+        let wasm = WitnessCalculator::new("./test-vectors/mycircuit.wasm").unwrap();
+        (r1cs, zkey, wasm)
+    }
+
+    fn mismatches(result: Result<(), ConsistencyError>) -> Vec<Mismatch> {
+        match result {
+            Err(ConsistencyError::Mismatches(mismatches)) => mismatches,
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn consistent() {
+        let (r1cs, zkey, wasm) = artifacts();
+        check_circuit(&r1cs, Some(&zkey), Some(&wasm)).unwrap();
+        check_circuit(&r1cs, None, None).unwrap();
+        check_circuit_hash(&zkey, &zkey.circuit_hash).unwrap();
+    }
+
+    #[test]
+    fn reports_every_mismatch() {
+        let (mut r1cs, zkey, wasm) = artifacts();
+        r1cs.n_wires = 5;
+        r1cs.n_pub_in = 1;
+        r1cs.n_constraints = 3;
+        let mismatches = mismatches(check_circuit(&r1cs, Some(&zkey), Some(&wasm)));
+        assert_eq!(
+            mismatches,
+            vec![
+                Mismatch::NVars {
+                    artifact: Artifact::Zkey,
+                    expected: 5,
+                    found: 4
+                },
+                Mismatch::NPublic {
+                    expected: 2,
+                    found: 1
+                },
+                Mismatch::DomainSize {
+                    domain_size: 4,
+                    required: 6
+                },
+                Mismatch::NVars {
+                    artifact: Artifact::Wasm,
+                    expected: 5,
+                    found: 4
+                },
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "circom-2")]
+    fn circom2_wasm() {
+        let r1cs =
+            R1CSFile::<Bn254>::new(File::open("./test-vectors/circom2_multiplier2.r1cs").unwrap())
+                .unwrap()
+                .header;
+        let wasm = WitnessCalculator::new("./test-vectors/circom2_multiplier2.wasm").unwrap();
+        assert!(!wasm.instance.has_function("getNVars"));
+        check_circuit(&r1cs, None, Some(&wasm)).unwrap();
+
+        let mut other = r1cs.clone();
+        other.n_wires += 1;
+        let mismatches = mismatches(check_circuit(&other, None, Some(&wasm)));
+        assert_eq!(
+            mismatches,
+            vec![Mismatch::NVars {
+                artifact: Artifact::Wasm,
+                expected: r1cs.n_wires as usize + 1,
+                found: r1cs.n_wires as usize,
+            }]
+        );
+    }
+
+    #[test]
+    fn prime_and_circuit_hash() {
+        let (mut r1cs, zkey, wasm) = artifacts();
+        r1cs.prime_size[0] ^= 1;
+        let mismatches = mismatches(check_circuit(&r1cs, None, Some(&wasm)));
+        assert!(matches!(mismatches[..], [Mismatch::Prime { .. }]));
+
+        let mut other = zkey.circuit_hash;
+        other[0] ^= 1;
+        let err = check_circuit_hash(&zkey, &other).unwrap_err();
+        assert_eq!(err, Mismatch::CircuitHash(zkey.circuit_hash));
+        assert!(err.to_string().contains("circuit hash 4289918f"));
+    }
+}
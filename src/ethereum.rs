@@ -3,9 +3,12 @@
 use ark_ff::{BigInteger, FromBytes, PrimeField};
 use ethers_core::types::U256;
 use num_traits::Zero;
+use std::convert::TryFrom;
 
 use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
 
+use crate::{circom::PublicSignals, InvalidPoint, Validation};
+
 pub struct Inputs(pub Vec<U256>);
 
 impl From<&[Fr]> for Inputs {
@@ -16,18 +19,24 @@ impl From<&[Fr]> for Inputs {
     }
 }
 
+impl From<&PublicSignals<Fr>> for Inputs {
+    fn from(src: &PublicSignals<Fr>) -> Self {
+        Self::from(&src[..])
+    }
+}
+
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct G1 {
     pub x: U256,
     pub y: U256,
 }
 
-impl From<G1> for G1Affine {
-    fn from(src: G1) -> G1Affine {
-        let x: Fq = u256_to_point(src.x);
-        let y: Fq = u256_to_point(src.y);
-        let inf = x.is_zero() && y.is_zero();
-        G1Affine::new(x, y, inf)
+/// Fully validates the point, see [`G1::to_affine`] for the other modes
+impl TryFrom<G1> for G1Affine {
+    type Error = InvalidPoint;
+
+    fn try_from(src: G1) -> Result<G1Affine, InvalidPoint> {
+        src.to_affine(Validation::Full)
     }
 }
 
@@ -37,10 +46,35 @@ impl G1 {
     pub fn as_tuple(&self) -> (U256, U256) {
         (self.x, self.y)
     }
+
+    /// Converts the point, checking it as set by `validation`. An invalid point is reported
+    /// at index 0.
+    pub fn to_affine(self, validation: Validation) -> Result<G1Affine, InvalidPoint> {
+        self.to_affine_at(validation, 0)
+    }
+
+    // reports an invalid point at `index`, its position in a proof or a verifying key
+    fn to_affine_at(self, validation: Validation, index: usize) -> Result<G1Affine, InvalidPoint> {
+        let coordinate =
+            |c| u256_to_point::<Fq>(c).ok_or(InvalidPoint::InvalidCoordinate { index });
+        let x = coordinate(self.x)?;
+        let y = coordinate(self.y)?;
+        // the EVM encodes the point at infinity as (0, 0)
+        let point = if x.is_zero() && y.is_zero() {
+            G1Affine::zero()
+        } else {
+            G1Affine::new(x, y, false)
+        };
+        validation.check_point(&point, index)?;
+        Ok(point)
+    }
 }
 
 impl From<&G1Affine> for G1 {
     fn from(p: &G1Affine) -> Self {
+        if p.is_zero() {
+            return Self::default();
+        }
         Self {
             x: point_to_u256(p.x),
             y: point_to_u256(p.y),
@@ -54,18 +88,12 @@ pub struct G2 {
     pub y: [U256; 2],
 }
 
-impl From<G2> for G2Affine {
-    fn from(src: G2) -> G2Affine {
-        let c0 = u256_to_point(src.x[0]);
-        let c1 = u256_to_point(src.x[1]);
-        let x = Fq2::new(c0, c1);
-
-        let c0 = u256_to_point(src.y[0]);
-        let c1 = u256_to_point(src.y[1]);
-        let y = Fq2::new(c0, c1);
+/// Fully validates the point, see [`G2::to_affine`] for the other modes
+impl TryFrom<G2> for G2Affine {
+    type Error = InvalidPoint;
 
-        let inf = x.is_zero() && y.is_zero();
-        G2Affine::new(x, y, inf)
+    fn try_from(src: G2) -> Result<G2Affine, InvalidPoint> {
+        src.to_affine(Validation::Full)
     }
 }
 
@@ -76,10 +104,34 @@ impl G2 {
     pub fn as_tuple(&self) -> G2Tup {
         ([self.x[1], self.x[0]], [self.y[1], self.y[0]])
     }
+
+    /// Converts the point, checking it as set by `validation`. An invalid point is reported
+    /// at index 0.
+    pub fn to_affine(self, validation: Validation) -> Result<G2Affine, InvalidPoint> {
+        self.to_affine_at(validation, 0)
+    }
+
+    // reports an invalid point at `index`, its position in a proof or a verifying key
+    fn to_affine_at(self, validation: Validation, index: usize) -> Result<G2Affine, InvalidPoint> {
+        let coordinate =
+            |c| u256_to_point::<Fq>(c).ok_or(InvalidPoint::InvalidCoordinate { index });
+        let x = Fq2::new(coordinate(self.x[0])?, coordinate(self.x[1])?);
+        let y = Fq2::new(coordinate(self.y[0])?, coordinate(self.y[1])?);
+        let point = if x.is_zero() && y.is_zero() {
+            G2Affine::zero()
+        } else {
+            G2Affine::new(x, y, false)
+        };
+        validation.check_point(&point, index)?;
+        Ok(point)
+    }
 }
 
 impl From<&G2Affine> for G2 {
     fn from(p: &G2Affine) -> Self {
+        if p.is_zero() {
+            return Self::default();
+        }
         Self {
             x: [point_to_u256(p.x.c0), point_to_u256(p.x.c1)],
             y: [point_to_u256(p.y.c0), point_to_u256(p.y.c1)],
@@ -110,13 +162,17 @@ impl From<ark_groth16::Proof<Bn254>> for Proof {
     }
 }
 
-impl From<Proof> for ark_groth16::Proof<Bn254> {
-    fn from(src: Proof) -> ark_groth16::Proof<Bn254> {
-        ark_groth16::Proof {
-            a: src.a.into(),
-            b: src.b.into(),
-            c: src.c.into(),
-        }
+/// Fully validates the points of the proof, reporting an invalid point at its position in
+/// `a, b, c`
+impl TryFrom<Proof> for ark_groth16::Proof<Bn254> {
+    type Error = InvalidPoint;
+
+    fn try_from(src: Proof) -> Result<ark_groth16::Proof<Bn254>, InvalidPoint> {
+        Ok(ark_groth16::Proof {
+            a: src.a.to_affine_at(Validation::Full, 0)?,
+            b: src.b.to_affine_at(Validation::Full, 1)?,
+            c: src.c.to_affine_at(Validation::Full, 2)?,
+        })
     }
 }
 
@@ -153,24 +209,42 @@ impl From<ark_groth16::VerifyingKey<Bn254>> for VerifyingKey {
     }
 }
 
-impl From<VerifyingKey> for ark_groth16::VerifyingKey<Bn254> {
-    fn from(src: VerifyingKey) -> ark_groth16::VerifyingKey<Bn254> {
-        ark_groth16::VerifyingKey {
-            alpha_g1: src.alpha1.into(),
-            beta_g2: src.beta2.into(),
-            gamma_g2: src.gamma2.into(),
-            delta_g2: src.delta2.into(),
-            gamma_abc_g1: src.ic.into_iter().map(Into::into).collect(),
-        }
+/// Fully validates the points of the verifying key, checking the IC points in parallel. An
+/// invalid point is reported at its position in `alpha1, beta2, gamma2, delta2, ic[0], ...`.
+impl TryFrom<VerifyingKey> for ark_groth16::VerifyingKey<Bn254> {
+    type Error = InvalidPoint;
+
+    fn try_from(src: VerifyingKey) -> Result<ark_groth16::VerifyingKey<Bn254>, InvalidPoint> {
+        let alpha_g1 = src.alpha1.to_affine_at(Validation::Full, 0)?;
+        let beta_g2 = src.beta2.to_affine_at(Validation::Full, 1)?;
+        let gamma_g2 = src.gamma2.to_affine_at(Validation::Full, 2)?;
+        let delta_g2 = src.delta2.to_affine_at(Validation::Full, 3)?;
+
+        const FIRST_IC: usize = 4;
+        let gamma_abc_g1 = src
+            .ic
+            .into_iter()
+            .enumerate()
+            .map(|(i, point)| point.to_affine_at(Validation::Unchecked, FIRST_IC + i))
+            .collect::<Result<Vec<_>, _>>()?;
+        Validation::Full.check_from(&gamma_abc_g1, FIRST_IC)?;
+
+        Ok(ark_groth16::VerifyingKey {
+            alpha_g1,
+            beta_g2,
+            gamma_g2,
+            delta_g2,
+            gamma_abc_g1,
+        })
     }
 }
 
-// Helper for converting a PrimeField to its U256 representation for Ethereum compatibility
-fn u256_to_point<F: PrimeField>(point: U256) -> F {
+// Helper for converting a U256 to a PrimeField, returning None if it is not reduced
+fn u256_to_point<F: PrimeField>(point: U256) -> Option<F> {
     let mut buf = [0; 32];
     point.to_little_endian(&mut buf);
     let bigint = F::BigInt::read(&buf[..]).expect("always works");
-    F::from_repr(bigint).expect("alwasy works")
+    F::from_repr(bigint)
 }
 
 // Helper for converting a PrimeField to its U256 representation for Ethereum compatibility
@@ -185,6 +259,7 @@ fn point_to_u256<F: PrimeField>(point: F) -> U256 {
 mod tests {
     use super::*;
     use ark_bn254::Fq;
+    use ark_ec::{AffineCurve, ProjectiveCurve};
 
     fn fq() -> Fq {
         Fq::from(2)
@@ -199,18 +274,18 @@ mod tests {
     }
 
     fn g1() -> G1Affine {
-        G1Affine::new(fq(), fq(), false)
+        G1Affine::prime_subgroup_generator().mul(fr()).into_affine()
     }
 
     fn g2() -> G2Affine {
-        G2Affine::new(fq2(), fq2(), false)
+        G2Affine::prime_subgroup_generator().mul(fr()).into_affine()
     }
 
     #[test]
     fn convert_fq() {
         let el = fq();
         let el2 = point_to_u256(el);
-        let el3: Fq = u256_to_point(el2);
+        let el3: Fq = u256_to_point(el2).unwrap();
         let el4 = point_to_u256(el3);
         assert_eq!(el, el3);
         assert_eq!(el2, el4);
@@ -220,7 +295,7 @@ mod tests {
     fn convert_fr() {
         let el = fr();
         let el2 = point_to_u256(el);
-        let el3: Fr = u256_to_point(el2);
+        let el3: Fr = u256_to_point(el2).unwrap();
         let el4 = point_to_u256(el3);
         assert_eq!(el, el3);
         assert_eq!(el2, el4);
@@ -230,7 +305,7 @@ mod tests {
     fn convert_g1() {
         let el = g1();
         let el2 = G1::from(&el);
-        let el3: G1Affine = el2.into();
+        let el3 = G1Affine::try_from(el2).unwrap();
         let el4 = G1::from(&el3);
         assert_eq!(el, el3);
         assert_eq!(el2, el4);
@@ -240,7 +315,7 @@ mod tests {
     fn convert_g2() {
         let el = g2();
         let el2 = G2::from(&el);
-        let el3: G2Affine = el2.into();
+        let el3 = G2Affine::try_from(el2).unwrap();
         let el4 = G2::from(&el3);
         assert_eq!(el, el3);
         assert_eq!(el2, el4);
@@ -256,7 +331,28 @@ mod tests {
             gamma_abc_g1: vec![g1(), g1(), g1()],
         };
         let vk_ethers = VerifyingKey::from(vk.clone());
-        let ark_vk: ark_groth16::VerifyingKey<Bn254> = vk_ethers.into();
+        let ark_vk = ark_groth16::VerifyingKey::try_from(vk_ethers).unwrap();
+        assert_eq!(ark_vk, vk);
+    }
+
+    #[test]
+    fn convert_infinity() {
+        // the point at infinity is (0, 0) both on chain and in zkeys
+        assert_eq!(G1::from(&G1Affine::zero()), G1::default());
+        assert_eq!(G2::from(&G2Affine::zero()), G2::default());
+        assert_eq!(G1Affine::try_from(G1::default()), Ok(G1Affine::zero()));
+        assert_eq!(G2Affine::try_from(G2::default()), Ok(G2Affine::zero()));
+
+        let vk = ark_groth16::VerifyingKey::<Bn254> {
+            alpha_g1: g1(),
+            beta_g2: g2(),
+            gamma_g2: g2(),
+            delta_g2: g2(),
+            gamma_abc_g1: vec![g1(), G1Affine::zero(), g1()],
+        };
+        let vk_ethers = VerifyingKey::from(vk.clone());
+        assert_eq!(vk_ethers.ic[1], G1::default());
+        let ark_vk = ark_groth16::VerifyingKey::try_from(vk_ethers).unwrap();
         assert_eq!(ark_vk, vk);
     }
 
@@ -268,7 +364,82 @@ mod tests {
             c: g1(),
         };
         let p2 = Proof::from(p.clone());
-        let p3 = ark_groth16::Proof::from(p2);
+        let p3 = ark_groth16::Proof::try_from(p2).unwrap();
         assert_eq!(p, p3);
     }
+
+    #[test]
+    fn reject_invalid_points() {
+        let off_curve = G1 {
+            x: point_to_u256(fq()),
+            y: point_to_u256(fq()),
+        };
+        assert_eq!(
+            G1Affine::try_from(off_curve),
+            Err(InvalidPoint::NotOnCurve { index: 0 })
+        );
+        assert!(off_curve.to_affine(Validation::Unchecked).is_ok());
+
+        let unreduced = G1 {
+            x: U256::MAX,
+            y: U256::zero(),
+        };
+        assert_eq!(
+            G1Affine::try_from(unreduced),
+            Err(InvalidPoint::InvalidCoordinate { index: 0 })
+        );
+
+        let off_curve = G2 {
+            x: [point_to_u256(fq2().c0), point_to_u256(fq2().c1)],
+            y: [point_to_u256(fq2().c0), point_to_u256(fq2().c1)],
+        };
+        let proof = Proof {
+            a: G1::from(&g1()),
+            b: off_curve,
+            c: G1::from(&g1()),
+        };
+        assert_eq!(
+            ark_groth16::Proof::try_from(proof),
+            Err(InvalidPoint::NotOnCurve { index: 1 })
+        );
+        let proof = Proof {
+            c: unreduced,
+            ..Proof::from(ark_groth16::Proof {
+                a: g1(),
+                b: g2(),
+                c: g1(),
+            })
+        };
+        assert_eq!(
+            ark_groth16::Proof::try_from(proof),
+            Err(InvalidPoint::InvalidCoordinate { index: 2 })
+        );
+
+        // points are numbered from alpha1 to the last IC point
+        let valid = VerifyingKey::from(ark_groth16::VerifyingKey::<Bn254> {
+            alpha_g1: g1(),
+            beta_g2: g2(),
+            gamma_g2: g2(),
+            delta_g2: g2(),
+            gamma_abc_g1: vec![g1(), g1(), g1()],
+        });
+        let mut vk = valid.clone();
+        vk.ic[2].y = point_to_u256(fq());
+        assert_eq!(
+            ark_groth16::VerifyingKey::try_from(vk),
+            Err(InvalidPoint::NotOnCurve { index: 6 })
+        );
+        let mut vk = valid.clone();
+        vk.ic[1] = unreduced;
+        assert_eq!(
+            ark_groth16::VerifyingKey::try_from(vk),
+            Err(InvalidPoint::InvalidCoordinate { index: 5 })
+        );
+        let mut vk = valid;
+        vk.gamma2.y[1] = U256::MAX;
+        assert_eq!(
+            ark_groth16::VerifyingKey::try_from(vk),
+            Err(InvalidPoint::InvalidCoordinate { index: 2 })
+        );
+    }
 }
@@ -2,12 +2,34 @@
 //!
 //! Provides bindings to Circom's R1CS, for Groth16 Proof and Witness generation in Rust.
 mod witness;
-pub use witness::WitnessCalculator;
+pub use witness::{
+    parse_inputs_json, CircomInputValue, CircomInputs, InputError, InputPolicy, InputSignals,
+    PooledWitnessCalculator, Witness, WitnessCalculator, WitnessCalculatorPool, WrongSize,
+};
+
+/// `#[derive(CircomInputs)]`, see [`CircomInputs`]
+pub use ark_circom_derive::CircomInputs;
+
+#[doc(hidden)]
+pub use num_bigint;
 
 pub mod circom;
-pub use circom::{CircomBuilder, CircomCircuit, CircomConfig, CircomReduction};
+pub use circom::{
+    small_scalar_msm, BatchProver, CircomBuilder, CircomCircuit, CircomConfig, CircomProver,
+    CircomReduction, PreparedProver, PublicSignals, StreamingProver,
+};
 
 pub mod ethereum;
 
 mod zkey;
-pub use zkey::read_zkey;
+pub use zkey::{
+    audit_proving_key, audit_zkey, hash_zkey, read_zkey, read_zkey_cache, read_zkey_cached,
+    read_zkey_header, read_zkey_vk, read_zkey_with_validation, write_zkey_cache, AuditReport,
+    CircuitHash, Compression, Issue, KeyPoint, Severity, Zkey, ZkeyError, ZkeyHash, ZkeyHeader,
+};
+
+mod consistency;
+pub use consistency::{check_circuit, check_circuit_hash, Artifact, ConsistencyError, Mismatch};
+
+mod validation;
+pub use validation::{InvalidPoint, Validation};
@@ -0,0 +1,136 @@
+//! Validation of curve points read from untrusted sources
+//!
+//! Points are checked to be on the curve and, for [`Validation::Full`], in the prime-order
+//! subgroup. The subgroup check costs a scalar multiplication per point, so slices of points
+//! are checked in parallel batches. Note that G1 of BN254 has a cofactor of 1, so every G1
+//! point on the curve is also in the subgroup, while G2 points must be checked separately.
+use ark_ec::{models::SWModelParameters, short_weierstrass_jacobian::GroupAffine};
+use rayon::prelude::*;
+
+// number of points checked by each rayon task
+const BATCH_SIZE: usize = 64;
+
+/// How thoroughly loaded curve points are checked
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Validation {
+    /// Points must be on the curve and in the prime-order subgroup
+    #[default]
+    Full,
+    /// Points must be on the curve, skipping the more expensive subgroup check
+    OnCurve,
+    /// No checks, for inputs which are trusted, e.g. generated locally
+    Unchecked,
+}
+
+/// A point that failed validation, with its position in the checked points
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidPoint {
+    #[error("point {index} has a coordinate outside of the base field")]
+    InvalidCoordinate { index: usize },
+    #[error("point {index} is not on the curve")]
+    NotOnCurve { index: usize },
+    #[error("point {index} is not in the prime-order subgroup")]
+    NotInSubgroup { index: usize },
+}
+
+impl Validation {
+    /// Checks the points, returning the first invalid one
+    pub fn check<P: SWModelParameters>(
+        self,
+        points: &[GroupAffine<P>],
+    ) -> Result<(), InvalidPoint> {
+        self.check_from(points, 0)
+    }
+
+    /// Same as [`Validation::check`] for a slice starting at point `first`, which invalid
+    /// points are numbered from
+    pub fn check_from<P: SWModelParameters>(
+        self,
+        points: &[GroupAffine<P>],
+        first: usize,
+    ) -> Result<(), InvalidPoint> {
+        if self == Validation::Unchecked {
+            return Ok(());
+        }
+
+        let index = points
+            .par_iter()
+            .with_min_len(BATCH_SIZE)
+            .position_first(|point| self.check_point(point, 0).is_err());
+        match index {
+            Some(index) => self.check_point(&points[index], first + index),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks a single point, reported as point `index` if invalid
+    pub fn check_point<P: SWModelParameters>(
+        self,
+        point: &GroupAffine<P>,
+        index: usize,
+    ) -> Result<(), InvalidPoint> {
+        match self {
+            Validation::Unchecked => Ok(()),
+            _ if !point.is_on_curve() => Err(InvalidPoint::NotOnCurve { index }),
+            Validation::Full if !point.is_in_correct_subgroup_assuming_on_curve() => {
+                Err(InvalidPoint::NotInSubgroup { index })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Fq, Fq2, G1Affine, G2Affine};
+    use ark_ec::AffineCurve;
+    use ark_ff::{Field, One, SquareRootField, Zero};
+
+    // a point on the twist which is not in the prime-order subgroup, found by incrementing x
+    fn g2_outside_subgroup() -> G2Affine {
+        let mut x = Fq2::one();
+        loop {
+            let y2 = x.square() * x + <ark_bn254::g2::Parameters as SWModelParameters>::COEFF_B;
+            if let Some(y) = y2.sqrt() {
+                let point = G2Affine::new(x, y, false);
+                if !point.is_in_correct_subgroup_assuming_on_curve() {
+                    return point;
+                }
+            }
+            x += Fq2::one();
+        }
+    }
+
+    #[test]
+    fn modes() {
+        let g1 = G1Affine::prime_subgroup_generator();
+        let off_curve = G1Affine::new(Fq::from(2), Fq::from(2), false);
+        let mut points = vec![g1; 200];
+        points[150] = off_curve;
+        points.push(off_curve);
+
+        assert_eq!(
+            Validation::Full.check(&points),
+            Err(InvalidPoint::NotOnCurve { index: 150 })
+        );
+        assert_eq!(
+            Validation::OnCurve.check(&points),
+            Err(InvalidPoint::NotOnCurve { index: 150 })
+        );
+        assert_eq!(Validation::Unchecked.check(&points), Ok(()));
+        assert_eq!(Validation::Full.check(&points[..150]), Ok(()));
+        assert_eq!(
+            Validation::Full.check_from(&points[100..], 100),
+            Err(InvalidPoint::NotOnCurve { index: 150 })
+        );
+
+        let g2 = G2Affine::prime_subgroup_generator();
+        let points = vec![g2, G2Affine::zero(), g2_outside_subgroup()];
+        assert_eq!(
+            Validation::Full.check(&points),
+            Err(InvalidPoint::NotInSubgroup { index: 2 })
+        );
+        assert_eq!(Validation::OnCurve.check(&points), Ok(()));
+    }
+}
@@ -1,5 +1,5 @@
 use color_eyre::Result;
-use wasmer::{Function, Instance, Value};
+use wasmer::{Function, Instance, Memory, Module, Value};
 
 #[derive(Clone, Debug)]
 pub struct Wasm(Instance);
@@ -31,9 +31,18 @@ pub trait Circom {
 pub trait Circom2 {
     fn get_field_num_len32(&self) -> Result<u32>;
     fn get_raw_prime(&self) -> Result<()>;
+    fn get_shared_rw_memory_start(&self) -> Result<u32>;
     fn read_shared_rw_memory(&self, i: u32) -> Result<u32>;
+    // kept for callers of the trait, the witness calculator writes the memory directly
+    #[cfg_attr(feature = "circom-2", allow(dead_code))]
+    #[deprecated(
+        note = "one call per limb is slow, write to the memory at `get_shared_rw_memory_start` instead"
+    )]
     fn write_shared_rw_memory(&self, i: u32, v: u32) -> Result<()>;
     fn set_input_signal(&self, hmsb: u32, hlsb: u32, pos: u32) -> Result<()>;
+    // Only exported by newer runtimes, see `Wasm::has_function`
+    fn get_input_signal_size(&self, hmsb: u32, hlsb: u32) -> Result<i32>;
+    fn get_input_size(&self) -> Result<u32>;
     fn get_witness(&self, i: u32) -> Result<()>;
     fn get_witness_size(&self) -> Result<u32>;
 }
@@ -60,6 +69,10 @@ impl Circom2 for Wasm {
         Ok(())
     }
 
+    fn get_shared_rw_memory_start(&self) -> Result<u32> {
+        self.get_u32("getSharedRWMemoryStart")
+    }
+
     fn read_shared_rw_memory(&self, i: u32) -> Result<u32> {
         let func = self.func("readSharedRWMemory");
         let result = func.call(&[i.into()])?;
@@ -78,6 +91,16 @@ impl Circom2 for Wasm {
         Ok(())
     }
 
+    fn get_input_signal_size(&self, hmsb: u32, hlsb: u32) -> Result<i32> {
+        let func = self.func("getInputSignalSize");
+        let result = func.call(&[hmsb.into(), hlsb.into()])?;
+        Ok(result[0].unwrap_i32())
+    }
+
+    fn get_input_size(&self) -> Result<u32> {
+        self.get_u32("getInputSize")
+    }
+
     fn get_witness(&self, i: u32) -> Result<()> {
         let func = self.func("getWitness");
         func.call(&[i.into()])?;
@@ -162,4 +185,19 @@ impl Wasm {
     pub fn new(instance: Instance) -> Self {
         Self(instance)
     }
+
+    /// Returns whether the module exports the function with the provided name
+    pub fn has_function(&self, name: &str) -> bool {
+        self.0.exports.get_function(name).is_ok()
+    }
+
+    /// Returns the memory exported by the module, if any. Circom 2 modules export their
+    /// own memory instead of importing the one provided by the host.
+    pub fn memory(&self) -> Option<Memory> {
+        self.0.exports.get_memory("memory").ok().cloned()
+    }
+
+    pub fn module(&self) -> &Module {
+        self.0.module()
+    }
 }
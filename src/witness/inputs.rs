@@ -0,0 +1,275 @@
+//! Validation of circuit inputs against the input signals declared by the circuit
+use std::{collections::HashMap, fmt};
+
+use num::Integer;
+use num_bigint::BigInt;
+
+use crate::circom::SymFile;
+
+/// The input signals of a circuit's main component, along with the number of field
+/// elements each of them expects once flattened.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InputSignals {
+    pub sizes: HashMap<String, usize>,
+}
+
+/// Report of every input which does not match the circuit's declared input signals
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InputError {
+    /// Inputs which are not input signals of the circuit
+    pub unknown: Vec<String>,
+    /// Input signals for which no values were provided
+    ///
+    /// Only filled when validating against the `.sym` file. The runtime's
+    /// `getInputSignalSize` only reports the size of the signals it is asked about, so
+    /// missing inputs are then counted in `missing_values` without their names.
+    pub missing: Vec<String>,
+    /// Inputs provided with the wrong number of values
+    pub wrong_size: Vec<WrongSize>,
+    /// Number of input values still expected by the circuit, when the runtime only
+    /// reports the total and the missing names are therefore unknown
+    pub missing_values: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WrongSize {
+    pub name: String,
+    pub expected: usize,
+    pub actual: usize,
+}
+
+/// How input values outside of `[0, p)` are handled before being fed to the circuit.
+/// Negative values always stand for their additive inverse, as in circom and snarkjs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputPolicy {
+    /// Reduce every value modulo the prime
+    #[default]
+    Reduce,
+    /// Reject values whose absolute value is not smaller than the prime, since they can
+    /// only be fed to the circuit by aliasing another value
+    Strict,
+}
+
+impl InputPolicy {
+    /// Maps `value` to its canonical representative in `[0, prime)`, or returns `None` if
+    /// the policy rejects it
+    pub fn normalize(&self, value: &BigInt, prime: &BigInt) -> Option<BigInt> {
+        if self == &InputPolicy::Strict && value.magnitude() >= prime.magnitude() {
+            return None;
+        }
+        Some(value.mod_floor(prime))
+    }
+}
+
+impl InputSignals {
+    /// Recovers the input signals from a symbol file. Circom lays out the witness as the
+    /// constant one, the outputs and then the public and private inputs of the main
+    /// component, so the inputs are the signals whose witness index falls in
+    /// `[1 + num_outputs, 1 + num_outputs + num_inputs)`.
+    pub fn from_sym(sym: &SymFile, num_outputs: usize, num_inputs: usize) -> Self {
+        let start = 1 + num_outputs;
+        let names = sym.names_by_witness_index(start..start + num_inputs);
+
+        let mut sizes = HashMap::new();
+        for name in names.values() {
+            *sizes.entry(input_name(name)).or_insert(0) += 1;
+        }
+
+        Self { sizes }
+    }
+
+    /// Checks the provided `(name, number of values)` pairs against the input signals
+    pub fn validate<'a>(
+        &self,
+        inputs: impl IntoIterator<Item = (&'a str, usize)>,
+    ) -> Result<(), InputError> {
+        let mut err = InputError::default();
+
+        let mut provided = HashMap::new();
+        for (name, len) in inputs {
+            *provided.entry(name).or_insert(0) += len;
+        }
+
+        for (&name, &actual) in &provided {
+            match self.sizes.get(name) {
+                None => err.unknown.push(name.to_string()),
+                Some(&expected) if expected != actual => err.wrong_size.push(WrongSize {
+                    name: name.to_string(),
+                    expected,
+                    actual,
+                }),
+                _ => {}
+            }
+        }
+
+        for name in self.sizes.keys() {
+            if !provided.contains_key(name.as_str()) {
+                err.missing.push(name.clone());
+            }
+        }
+
+        err.into_result()
+    }
+}
+
+impl InputError {
+    pub(crate) fn into_result(mut self) -> Result<(), InputError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        self.unknown.sort();
+        self.missing.sort();
+        self.wrong_size.sort_by(|a, b| a.name.cmp(&b.name));
+        Err(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.unknown.is_empty()
+            && self.missing.is_empty()
+            && self.wrong_size.is_empty()
+            && self.missing_values == 0
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid circuit inputs")?;
+        if !self.unknown.is_empty() {
+            write!(f, "; unknown: {}", self.unknown.join(", "))?;
+        }
+        if !self.missing.is_empty() {
+            write!(f, "; missing: {}", self.missing.join(", "))?;
+        }
+        for w in &self.wrong_size {
+            write!(
+                f,
+                "; {} expects {} values, got {}",
+                w.name, w.expected, w.actual
+            )?;
+        }
+        if self.missing_values > 0 {
+            write!(f, "; {} input values missing", self.missing_values)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InputError {}
+
+// `main.in[2][1]` is fed as `in`, and bus fields as `in.x`
+fn input_name(symbol: &str) -> String {
+    let name = symbol.strip_prefix("main.").unwrap_or(symbol);
+    let mut res = String::with_capacity(name.len());
+    let mut depth = 0;
+    for c in name.chars() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ if depth == 0 => res.push(c),
+            _ => {}
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::str::FromStr;
+
+    fn signals() -> InputSignals {
+        let sym = "1,1,0,main.out\n\
+                   2,2,0,main.a\n\
+                   3,3,0,main.b[0]\n\
+                   4,4,0,main.b[1]\n\
+                   5,2,1,main.sub.in\n\
+                   6,5,1,main.sub.x\n";
+        let sym = SymFile::new(sym.as_bytes()).unwrap();
+        InputSignals::from_sym(&sym, 1, 3)
+    }
+
+    #[test]
+    fn from_sym() {
+        let signals = signals();
+        assert_eq!(signals.sizes.len(), 2);
+        assert_eq!(signals.sizes["a"], 1);
+        assert_eq!(signals.sizes["b"], 2);
+    }
+
+    #[test]
+    fn valid() {
+        signals().validate(vec![("a", 1), ("b", 2)]).unwrap();
+    }
+
+    #[test]
+    fn report() {
+        let err = signals().validate(vec![("b", 3), ("foo", 1)]).unwrap_err();
+        assert_eq!(err.unknown, vec!["foo".to_string()]);
+        assert_eq!(err.missing, vec!["a".to_string()]);
+        assert_eq!(
+            err.wrong_size,
+            vec![WrongSize {
+                name: "b".to_string(),
+                expected: 2,
+                actual: 3
+            }]
+        );
+    }
+
+    fn prime() -> BigInt {
+        BigInt::from_str(
+            "21888242871839275222246405745257275088548364400416034343698204186575808495617",
+        )
+        .unwrap()
+    }
+
+    fn bigint() -> impl Strategy<Value = BigInt> {
+        prop::collection::vec(any::<u32>(), 0..12).prop_flat_map(|digits| {
+            any::<bool>().prop_map(move |negative| {
+                let sign = if negative {
+                    num_bigint::Sign::Minus
+                } else {
+                    num_bigint::Sign::Plus
+                };
+                BigInt::from_slice(sign, &digits)
+            })
+        })
+    }
+
+    #[test]
+    fn normalize() {
+        let p = prime();
+        let policy = InputPolicy::Strict;
+        assert_eq!(policy.normalize(&BigInt::from(-1), &p), Some(&p - 1));
+        assert_eq!(policy.normalize(&(&p - 1), &p), Some(&p - 1));
+        assert_eq!(policy.normalize(&p, &p), None);
+        assert_eq!(policy.normalize(&-&p, &p), None);
+        assert_eq!(
+            InputPolicy::Reduce.normalize(&(&p + 1), &p),
+            Some(BigInt::from(1))
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn reduce_is_canonical(value in bigint()) {
+            let p = prime();
+            let res = InputPolicy::Reduce.normalize(&value, &p).unwrap();
+            prop_assert!(res >= BigInt::from(0) && res < p);
+            prop_assert_eq!((&res - &value).mod_floor(&p), BigInt::from(0));
+        }
+
+        #[test]
+        fn strict_agrees_with_reduce(value in bigint()) {
+            let p = prime();
+            match InputPolicy::Strict.normalize(&value, &p) {
+                Some(res) => prop_assert_eq!(
+                    Some(res),
+                    InputPolicy::Reduce.normalize(&value, &p)
+                ),
+                None => prop_assert!(value.magnitude() >= p.magnitude()),
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Parsing of circuit inputs from the `input.json` format accepted by snarkjs
+use std::{collections::HashMap, str::FromStr};
+
+use color_eyre::{eyre::eyre, Result};
+use num_bigint::BigInt;
+use num_traits::Num;
+use serde_json::Value;
+
+/// Parses a JSON object of circuit inputs. Multi-dimensional arrays are flattened in
+/// circom's row-major order and nested objects, used for circom 2.2 buses, are turned into
+/// dotted names such as `in.x` or `in[1].x`. Values can be numbers, decimal or `0x`
+/// prefixed hex strings, optionally negative, or booleans.
+pub fn parse_inputs_json(json: &str) -> Result<HashMap<String, Vec<BigInt>>> {
+    let value: Value = serde_json::from_str(json)?;
+    let fields = value
+        .as_object()
+        .ok_or_else(|| eyre!("circuit inputs must be a JSON object"))?;
+
+    let mut inputs = HashMap::new();
+    for (name, value) in fields {
+        flatten_signal(name.clone(), value, &mut inputs)?;
+    }
+
+    Ok(inputs)
+}
+
+fn flatten_signal(
+    name: String,
+    value: &Value,
+    inputs: &mut HashMap<String, Vec<BigInt>>,
+) -> Result<()> {
+    match value {
+        Value::Object(fields) => {
+            for (field, value) in fields {
+                flatten_signal(format!("{}.{}", name, field), value, inputs)?;
+            }
+        }
+        Value::Array(items) if is_bus_array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                flatten_signal(format!("{}[{}]", name, i), item, inputs)?;
+            }
+        }
+        _ => {
+            let values = inputs.entry(name.clone()).or_default();
+            flatten_values(&name, value, values)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn flatten_values(name: &str, value: &Value, values: &mut Vec<BigInt>) -> Result<()> {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                flatten_values(name, item, values)?;
+            }
+        }
+        value => values.push(
+            parse_value(value)
+                .map_err(|err| eyre!("invalid value for input `{}`: {}", name, err))?,
+        ),
+    }
+
+    Ok(())
+}
+
+// arrays of buses are arrays whose innermost elements are objects
+fn is_bus_array(items: &[Value]) -> bool {
+    match items.first() {
+        Some(Value::Object(_)) => true,
+        Some(Value::Array(inner)) => is_bus_array(inner),
+        _ => false,
+    }
+}
+
+fn parse_value(value: &Value) -> Result<BigInt> {
+    match value {
+        Value::Bool(b) => Ok(BigInt::from(*b as u8)),
+        Value::Number(num) => {
+            if let Some(num) = num.as_u64() {
+                Ok(BigInt::from(num))
+            } else if let Some(num) = num.as_i64() {
+                Ok(BigInt::from(num))
+            } else {
+                Err(eyre!(
+                    "{} is not an integer, large values must be passed as strings",
+                    num
+                ))
+            }
+        }
+        Value::String(s) => parse_str(s.trim()).ok_or_else(|| eyre!("{:?} is not a number", s)),
+        other => Err(eyre!("unsupported value {}", other)),
+    }
+}
+
+fn parse_str(s: &str) -> Option<BigInt> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s),
+    };
+
+    let num = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => BigInt::from_str_radix(hex, 16).ok()?,
+        None => BigInt::from_str(digits).ok()?,
+    };
+
+    Some(if negative { -num } else { num })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(values: &[i64]) -> Vec<BigInt> {
+        values.iter().map(|v| BigInt::from(*v)).collect()
+    }
+
+    #[test]
+    fn values() {
+        let inputs = parse_inputs_json(
+            r#"{
+                "a": 3,
+                "b": "11",
+                "c": "0x1F",
+                "d": "-5",
+                "e": -7,
+                "f": true,
+                "g": "21888242871839275222246405745257275088548364400416034343698204186575808495616"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(inputs["a"], big(&[3]));
+        assert_eq!(inputs["b"], big(&[11]));
+        assert_eq!(inputs["c"], big(&[31]));
+        assert_eq!(inputs["d"], big(&[-5]));
+        assert_eq!(inputs["e"], big(&[-7]));
+        assert_eq!(inputs["f"], big(&[1]));
+        assert_eq!(
+            inputs["g"],
+            vec![BigInt::from_str(
+                "21888242871839275222246405745257275088548364400416034343698204186575808495616"
+            )
+            .unwrap()]
+        );
+    }
+
+    #[test]
+    fn nested_arrays() {
+        let inputs = parse_inputs_json(r#"{"m": [[1, 2, 3], [4, "5", false]]}"#).unwrap();
+        assert_eq!(inputs["m"], big(&[1, 2, 3, 4, 5, 0]));
+    }
+
+    #[test]
+    fn buses() {
+        let inputs =
+            parse_inputs_json(r#"{"p": {"x": 1, "y": [2, 3]}, "q": [{"x": 4}, {"x": 5}]}"#)
+                .unwrap();
+        assert_eq!(inputs["p.x"], big(&[1]));
+        assert_eq!(inputs["p.y"], big(&[2, 3]));
+        assert_eq!(inputs["q[0].x"], big(&[4]));
+        assert_eq!(inputs["q[1].x"], big(&[5]));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse_inputs_json(r#"[1, 2]"#).is_err());
+        assert!(parse_inputs_json(r#"{"a": 1.5}"#).is_err());
+        assert!(parse_inputs_json(r#"{"a": "abc"}"#).is_err());
+        assert!(parse_inputs_json(r#"{"a": null}"#).is_err());
+    }
+}
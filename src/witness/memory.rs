@@ -4,11 +4,12 @@ use wasmer::{Memory, MemoryView};
 
 // TODO: Decide whether we want Ark here or if it should use a generic BigInt package
 use ark_bn254::FrParameters;
-use ark_ff::{BigInteger, BigInteger256, FpParameters, FromBytes, Zero};
+use ark_ff::{BigInteger256, FpParameters, FromBytes, PrimeField, Zero};
 
-use num_bigint::{BigInt, BigUint};
+use num::Integer;
+use num_bigint::{BigInt, BigUint, Sign};
 
-use color_eyre::Result;
+use color_eyre::{eyre::eyre, Result};
 use std::str::FromStr;
 use std::{convert::TryFrom, ops::Deref};
 
@@ -102,7 +103,8 @@ impl SafeMemory {
     }
 
     /// Writes a Field Element to memory at the specified offset, truncating
-    /// to smaller u32 types if needed and adjusting the sign via 2s complement
+    /// to smaller u32 types if needed and adjusting the sign via 2s complement.
+    /// Values which don't fit in the short form are reduced modulo the prime.
     pub fn write_fr(&mut self, ptr: usize, fr: &BigInt) -> Result<()> {
         if fr < &self.short_max && fr > &self.short_min {
             if fr >= &BigInt::zero() {
@@ -111,7 +113,8 @@ impl SafeMemory {
                 self.write_short_negative(ptr, fr)?;
             }
         } else {
-            self.write_long_normal(ptr, fr)?;
+            let fr = fr.mod_floor(&self.prime);
+            self.write_long_normal(ptr, &fr)?;
         }
 
         Ok(())
@@ -139,6 +142,41 @@ impl SafeMemory {
         Ok(res)
     }
 
+    /// Reads a Field Element from the memory at the specified offset straight into an
+    /// arkworks field element. `r_inv` must be [`SafeMemory::montgomery_r_inv`], which
+    /// callers are expected to compute once per witness.
+    pub fn read_fr_element<F: PrimeField>(&self, ptr: usize, r_inv: &F) -> Result<F> {
+        let view = self.memory.view::<u8>();
+
+        let res = if view[ptr + 4 + 3].get() & 0x80 != 0 {
+            let num = self.read_field::<F>(ptr + 8)?;
+            if view[ptr + 4 + 3].get() & 0x40 != 0 {
+                num * r_inv
+            } else {
+                num
+            }
+        } else {
+            // short form, stored as a 2s complement i32
+            let num = self.read_u32(ptr) as i32;
+            if num < 0 {
+                -F::from(num.unsigned_abs())
+            } else {
+                F::from(num as u32)
+            }
+        };
+
+        Ok(res)
+    }
+
+    /// Returns the inverse of the Montgomery factor used by the runtime for long values
+    pub fn montgomery_r_inv<F: PrimeField>(&self) -> F {
+        let n64 = (self.n32 as u64 * 32 - 1) / 64 + 1;
+        F::from(2u64)
+            .pow([64 * n64])
+            .inverse()
+            .expect("2 is invertible in any odd prime field")
+    }
+
     fn write_short_positive(&mut self, ptr: usize, fr: &BigInt) -> Result<()> {
         let num = fr.to_i32().expect("not a short positive");
         self.write_u32(ptr, num as u32);
@@ -171,17 +209,37 @@ impl SafeMemory {
     fn write_big(&self, ptr: usize, num: &BigInt) -> Result<()> {
         let buf = unsafe { self.memory.data_unchecked_mut() };
 
-        // TODO: How do we handle negative bignums?
-        let (_, num) = num.clone().into_parts();
-        let num = BigInteger256::try_from(num).unwrap();
+        let (sign, bytes) = num.to_bytes_le();
+        let len = self.n32 * 4;
+        if sign == Sign::Minus || bytes.len() > len {
+            return Err(eyre!("{} does not fit in {} u32 words", num, self.n32));
+        }
 
-        let bytes = num.to_bytes_le();
-        let len = bytes.len();
-        buf[ptr..ptr + len].copy_from_slice(&bytes);
+        buf[ptr..ptr + bytes.len()].copy_from_slice(&bytes);
+        for b in &mut buf[ptr + bytes.len()..ptr + len] {
+            *b = 0;
+        }
 
         Ok(())
     }
 
+    /// Reads a field element stored as `n32` little-endian u32 words at the specified
+    /// memory offset straight into its arkworks representation, without going through
+    /// an intermediate BigInt
+    pub fn read_field<F: PrimeField>(&self, ptr: usize) -> Result<F> {
+        let buf = unsafe { self.memory.data_unchecked() };
+        let buf = &buf[ptr..ptr + self.n32 * 4];
+
+        let mut repr = F::BigInt::default();
+        for (limb, bytes) in repr.as_mut().iter_mut().zip(buf.chunks(8)) {
+            let mut le = [0u8; 8];
+            le[..bytes.len()].copy_from_slice(bytes);
+            *limb = u64::from_le_bytes(le);
+        }
+
+        F::from_repr(repr).ok_or_else(|| eyre!("value at offset {} is not a field element", ptr))
+    }
+
     /// Reads `num_bytes * 32` from the specified memory offset in a Big Integer
     pub fn read_big(&self, ptr: usize, num_bytes: usize) -> Result<BigInt> {
         let buf = unsafe { self.memory.data_unchecked() };
@@ -211,7 +269,7 @@ mod tests {
     fn new() -> SafeMemory {
         SafeMemory::new(
             Memory::new(&Store::default(), MemoryType::new(1, None, false)).unwrap(),
-            2,
+            8,
             BigInt::from_str(
                 "21888242871839275222246405745257275088548364400416034343698204186575808495617",
             )
@@ -255,11 +313,22 @@ mod tests {
         read_write_fr(BigInt::from(500000000000i64));
     }
 
-    // TODO: How should this be handled?
     #[test]
-    #[ignore]
     fn read_write_fr_big_negative() {
-        read_write_fr(BigInt::from_str("-500000000000").unwrap())
+        let mut mem = new();
+        let num = BigInt::from_str("-500000000000").unwrap();
+        mem.write_fr(0, &num).unwrap();
+        let res = mem.read_fr(0).unwrap();
+        assert_eq!(res, &mem.prime + num);
+    }
+
+    #[test]
+    fn read_write_fr_out_of_range() {
+        let mut mem = new();
+        let num = &mem.prime * 3 + 7_000_000_000i64;
+        mem.write_fr(0, &num).unwrap();
+        let res = mem.read_fr(0).unwrap();
+        assert_eq!(res, BigInt::from(7_000_000_000i64));
     }
 
     fn read_write_fr(num: BigInt) {
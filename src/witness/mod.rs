@@ -1,6 +1,21 @@
 mod witness_calculator;
 pub use witness_calculator::WitnessCalculator;
 
+mod pool;
+pub use pool::{PooledWitnessCalculator, WitnessCalculatorPool};
+
+mod inputs;
+pub use inputs::{InputError, InputPolicy, InputSignals, WrongSize};
+
+mod json;
+pub use json::parse_inputs_json;
+
+mod named;
+pub use named::Witness;
+
+mod typed;
+pub use typed::{CircomInputValue, CircomInputs};
+
 mod memory;
 pub(super) use memory::SafeMemory;
 
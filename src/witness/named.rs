@@ -0,0 +1,243 @@
+//! Witness values paired with the circuit's symbol table, to access signals by name
+use std::{collections::HashMap, ops::Range};
+
+use num_bigint::BigInt;
+use serde_json::{Map, Value};
+
+use crate::circom::SymFile;
+
+/// A witness together with the `.sym` table of its circuit, so that any signal which was
+/// not optimized out can be read by its circom path, e.g. `main.hasher.out`.
+#[derive(Clone, Debug)]
+pub struct Witness {
+    pub values: Vec<BigInt>,
+    pub sym: SymFile,
+    // signal name -> witness index
+    indices: HashMap<String, usize>,
+}
+
+impl Witness {
+    pub fn new(values: Vec<BigInt>, sym: SymFile) -> Self {
+        let indices = sym
+            .symbols
+            .iter()
+            .filter_map(|s| match s.witness_index {
+                Some(index) if index < values.len() => Some((s.name.clone(), index)),
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            values,
+            sym,
+            indices,
+        }
+    }
+
+    /// Returns the value of the signal at `path`. The `main.` prefix may be omitted.
+    /// Returns `None` for unknown signals and signals optimized out of the witness.
+    pub fn get(&self, path: &str) -> Option<&BigInt> {
+        self.index(path).map(|index| &self.values[index])
+    }
+
+    /// Returns the value of every signal matching `pattern`, in symbol file order. `*`
+    /// matches any sequence of characters and `?` any single character, while brackets
+    /// are matched literally, so `main.levels[*].selector` selects every level.
+    pub fn glob(&self, pattern: &str) -> Vec<(&str, &BigInt)> {
+        let pattern = pattern.as_bytes();
+        self.signals()
+            .filter(|(name, _)| glob_match(pattern, name.as_bytes()))
+            .collect()
+    }
+
+    /// Returns the values of the array of signals at `path`, flattened in circom's
+    /// row-major order, e.g. `main.out` for `main.out[0][0]`, `main.out[0][1]`, ...
+    /// Returns `None` if no element of the array is part of the witness.
+    pub fn array(&self, path: &str) -> Option<Vec<&BigInt>> {
+        let path = self.resolve_prefix(path);
+        let mut elements = self
+            .signals()
+            .filter_map(|(name, value)| {
+                array_indices(name.strip_prefix(path.as_str())?).map(|indices| (indices, value))
+            })
+            .collect::<Vec<_>>();
+        if elements.is_empty() {
+            return None;
+        }
+
+        elements.sort_by(|a, b| a.0.cmp(&b.0));
+        Some(elements.into_iter().map(|(_, value)| value).collect())
+    }
+
+    /// Returns the `range` of the flattened array of signals at `path`, see
+    /// [`Witness::array`]
+    pub fn slice(&self, path: &str, range: Range<usize>) -> Option<Vec<&BigInt>> {
+        let array = self.array(path)?;
+        array.get(range).map(|slice| slice.to_vec())
+    }
+
+    /// Exports every named signal of the witness as a JSON object mapping its path to its
+    /// value as a decimal string
+    pub fn to_json(&self) -> Value {
+        let map = self
+            .signals()
+            .map(|(name, value)| (name.to_string(), Value::String(value.to_string())))
+            .collect::<Map<_, _>>();
+        Value::Object(map)
+    }
+
+    // every signal in the witness, in symbol file order
+    fn signals(&self) -> impl Iterator<Item = (&str, &BigInt)> {
+        self.sym
+            .symbols
+            .iter()
+            .filter_map(move |s| match s.witness_index {
+                Some(index) if index < self.values.len() => {
+                    Some((s.name.as_str(), &self.values[index]))
+                }
+                _ => None,
+            })
+    }
+
+    fn index(&self, path: &str) -> Option<usize> {
+        self.indices
+            .get(path)
+            .or_else(|| self.indices.get(&format!("main.{}", path)))
+            .copied()
+    }
+
+    // adds the `main.` prefix unless `path` already has it
+    fn resolve_prefix(&self, path: &str) -> String {
+        if path == "main" || path.starts_with("main.") {
+            path.to_string()
+        } else {
+            format!("main.{}", path)
+        }
+    }
+}
+
+// parses a suffix made only of `[i]` groups
+fn array_indices(suffix: &str) -> Option<Vec<usize>> {
+    let mut indices = Vec::new();
+    let mut rest = suffix;
+    while !rest.is_empty() {
+        let end = rest.find(']')?;
+        indices.push(rest.strip_prefix('[')?[..end - 1].parse().ok()?);
+        rest = &rest[end + 1..];
+    }
+
+    if indices.is_empty() {
+        None
+    } else {
+        Some(indices)
+    }
+}
+
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    // position of the last `*` and the name position it was tried at, for backtracking
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    p = sp + 1;
+                    n = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn witness() -> Witness {
+        let sym = "1,1,0,main.out\n\
+                   2,2,0,main.in[0]\n\
+                   3,3,0,main.in[1]\n\
+                   4,4,0,main.m[0][0]\n\
+                   5,5,0,main.m[0][1]\n\
+                   6,6,0,main.m[1][0]\n\
+                   7,7,0,main.m[1][1]\n\
+                   8,2,1,main.levels[0].selector\n\
+                   9,3,2,main.levels[1].selector\n\
+                   10,-1,2,main.levels[1].tmp\n";
+        let sym = SymFile::new(sym.as_bytes()).unwrap();
+        Witness::new((0..8).map(|i| BigInt::from(i * 10)).collect(), sym)
+    }
+
+    fn values(values: &[i64]) -> Vec<BigInt> {
+        values.iter().map(|v| BigInt::from(*v)).collect()
+    }
+
+    #[test]
+    fn get() {
+        let w = witness();
+        assert_eq!(w.get("main.out"), Some(&BigInt::from(10)));
+        assert_eq!(w.get("in[1]"), Some(&BigInt::from(30)));
+        assert_eq!(w.get("main.levels[1].selector"), Some(&BigInt::from(30)));
+        assert_eq!(w.get("main.levels[1].tmp"), None);
+        assert_eq!(w.get("main.foo"), None);
+    }
+
+    #[test]
+    fn glob() {
+        let w = witness();
+        let selectors = w.glob("main.levels[*].selector");
+        assert_eq!(
+            selectors,
+            vec![
+                ("main.levels[0].selector", &BigInt::from(20)),
+                ("main.levels[1].selector", &BigInt::from(30)),
+            ]
+        );
+        assert_eq!(w.glob("main.m[?][1]").len(), 2);
+        assert_eq!(w.glob("*").len(), 9);
+        assert!(w.glob("main.x*").is_empty());
+    }
+
+    #[test]
+    fn array() {
+        let w = witness();
+        let m = w.array("main.m").unwrap();
+        assert_eq!(
+            m.into_iter().cloned().collect::<Vec<_>>(),
+            values(&[40, 50, 60, 70])
+        );
+        let row = w.array("m[1]").unwrap();
+        assert_eq!(
+            row.into_iter().cloned().collect::<Vec<_>>(),
+            values(&[60, 70])
+        );
+        let slice = w.slice("main.m", 1..3).unwrap();
+        assert_eq!(
+            slice.into_iter().cloned().collect::<Vec<_>>(),
+            values(&[50, 60])
+        );
+        assert!(w.slice("main.m", 3..5).is_none());
+        assert!(w.array("main.out").is_none());
+        assert!(w.array("main.levels").is_none());
+    }
+
+    #[test]
+    fn to_json() {
+        let json = witness().to_json();
+        assert_eq!(json["main.out"], "10");
+        assert_eq!(json["main.m[1][0]"], "60");
+        assert!(json.get("main.levels[1].tmp").is_none());
+    }
+}
@@ -0,0 +1,150 @@
+//! A pool of witness calculators sharing the same compiled module
+use color_eyre::Result;
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{Condvar, Mutex, PoisonError},
+};
+
+use super::WitnessCalculator;
+
+/// Witness calculators instantiating the same WASM module, each with its own memory, to
+/// compute witnesses concurrently
+///
+/// The module is compiled once; every calculator of the pool is a new instance of it, see
+/// [`WitnessCalculator::instantiate`].
+#[derive(Debug)]
+pub struct WitnessCalculatorPool {
+    calculators: Mutex<Vec<WitnessCalculator>>,
+    returned: Condvar,
+    size: usize,
+}
+
+impl WitnessCalculatorPool {
+    /// Compiles the wasm and instantiates it `size` times
+    pub fn new(wasm: impl AsRef<std::path::Path>, size: usize) -> Result<Self> {
+        Self::from_calculator(WitnessCalculator::new(wasm)?, size)
+    }
+
+    /// Fills the pool with `calculator` and `size - 1` other instances of its module, which
+    /// keep its input policy
+    pub fn from_calculator(calculator: WitnessCalculator, size: usize) -> Result<Self> {
+        let mut calculators = Vec::with_capacity(size.max(1));
+        for _ in 1..size {
+            calculators.push(calculator.instantiate()?);
+        }
+        calculators.push(calculator);
+
+        Ok(Self {
+            size: calculators.len(),
+            calculators: Mutex::new(calculators),
+            returned: Condvar::new(),
+        })
+    }
+
+    /// The number of calculators, i.e. of witnesses which can be computed concurrently
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Takes a calculator out of the pool, waiting for one to be returned if they are all
+    /// in use. The calculator goes back to the pool when the guard is dropped.
+    pub fn get(&self) -> PooledWitnessCalculator<'_> {
+        // the lock is never held while computing a witness, so a poisoned pool is still valid
+        let mut calculators = self
+            .calculators
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        loop {
+            if let Some(calculator) = calculators.pop() {
+                return PooledWitnessCalculator {
+                    pool: self,
+                    calculator: Some(calculator),
+                };
+            }
+            calculators = self
+                .returned
+                .wait(calculators)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
+
+/// A calculator taken out of a [`WitnessCalculatorPool`], returned to it when dropped
+#[derive(Debug)]
+pub struct PooledWitnessCalculator<'a> {
+    pool: &'a WitnessCalculatorPool,
+    // only `None` while being dropped
+    calculator: Option<WitnessCalculator>,
+}
+
+impl Deref for PooledWitnessCalculator<'_> {
+    type Target = WitnessCalculator;
+
+    fn deref(&self) -> &WitnessCalculator {
+        self.calculator.as_ref().expect("taken out on drop")
+    }
+}
+
+impl DerefMut for PooledWitnessCalculator<'_> {
+    fn deref_mut(&mut self) -> &mut WitnessCalculator {
+        self.calculator.as_mut().expect("taken out on drop")
+    }
+}
+
+impl Drop for PooledWitnessCalculator<'_> {
+    fn drop(&mut self) {
+        if let Some(calculator) = self.calculator.take() {
+            self.pool
+                .calculators
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(calculator);
+            self.pool.returned.notify_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Bn254, Fr};
+    use num_bigint::BigInt;
+    use std::thread;
+
+    #[test]
+    fn concurrent_witnesses() {
+        let pool = WitnessCalculatorPool::new("./test-vectors/mycircuit.wasm", 2).unwrap();
+        assert_eq!(pool.size(), 2);
+
+        let witnesses = thread::scope(|scope| {
+            let handles = (1..=8u64)
+                .map(|a| {
+                    let pool = &pool;
+                    scope.spawn(move || {
+                        let inputs = vec![
+                            ("a".to_string(), vec![BigInt::from(a)]),
+                            ("b".to_string(), vec![BigInt::from(3)]),
+                        ];
+                        pool.get()
+                            .calculate_witness_element::<Bn254, _>(inputs, false)
+                            .unwrap()
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        for (a, witness) in (1..=8u64).zip(witnesses) {
+            assert_eq!(witness[1], Fr::from(3 * a));
+        }
+        // every calculator went back to the pool
+        let (first, second) = (pool.get(), pool.get());
+        assert_ne!(
+            first.memory.memory.data_ptr(),
+            second.memory.memory.data_ptr()
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Conversion of typed Rust values into circuit inputs
+use std::collections::HashMap;
+
+use ark_ff::{BigInteger, Fp256, Fp256Parameters, Fp384, Fp384Parameters, PrimeField};
+use ethers_core::types::U256;
+use num_bigint::{BigInt, BigUint, Sign};
+
+/// A set of named circuit inputs, usually implemented with `#[derive(CircomInputs)]`
+pub trait CircomInputs {
+    /// Returns the values of every input signal, as consumed by
+    /// [`crate::WitnessCalculator::calculate_witness`]
+    fn to_inputs(&self) -> HashMap<String, Vec<BigInt>>;
+}
+
+impl CircomInputs for HashMap<String, Vec<BigInt>> {
+    fn to_inputs(&self) -> HashMap<String, Vec<BigInt>> {
+        self.clone()
+    }
+}
+
+/// A value which can be fed to a single input signal, arrays being flattened in circom's
+/// row-major order
+pub trait CircomInputValue {
+    fn push_values(&self, values: &mut Vec<BigInt>);
+
+    fn to_values(&self) -> Vec<BigInt> {
+        let mut values = Vec::new();
+        self.push_values(&mut values);
+        values
+    }
+}
+
+macro_rules! impl_input_value_int {
+    ($($ty:ty),*) => {
+        $(
+            impl CircomInputValue for $ty {
+                fn push_values(&self, values: &mut Vec<BigInt>) {
+                    values.push(BigInt::from(*self));
+                }
+            }
+        )*
+    };
+}
+
+impl_input_value_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl CircomInputValue for bool {
+    fn push_values(&self, values: &mut Vec<BigInt>) {
+        values.push(BigInt::from(*self as u8));
+    }
+}
+
+impl CircomInputValue for BigInt {
+    fn push_values(&self, values: &mut Vec<BigInt>) {
+        values.push(self.clone());
+    }
+}
+
+impl CircomInputValue for BigUint {
+    fn push_values(&self, values: &mut Vec<BigInt>) {
+        values.push(BigInt::from(self.clone()));
+    }
+}
+
+impl CircomInputValue for U256 {
+    fn push_values(&self, values: &mut Vec<BigInt>) {
+        let mut bytes = [0u8; 32];
+        self.to_little_endian(&mut bytes);
+        values.push(BigInt::from_bytes_le(Sign::Plus, &bytes));
+    }
+}
+
+impl<P: Fp256Parameters> CircomInputValue for Fp256<P> {
+    fn push_values(&self, values: &mut Vec<BigInt>) {
+        values.push(field_to_bigint(self));
+    }
+}
+
+impl<P: Fp384Parameters> CircomInputValue for Fp384<P> {
+    fn push_values(&self, values: &mut Vec<BigInt>) {
+        values.push(field_to_bigint(self));
+    }
+}
+
+fn field_to_bigint<F: PrimeField>(value: &F) -> BigInt {
+    BigInt::from_bytes_le(Sign::Plus, &value.into_repr().to_bytes_le())
+}
+
+impl<T: CircomInputValue, const N: usize> CircomInputValue for [T; N] {
+    fn push_values(&self, values: &mut Vec<BigInt>) {
+        self.as_slice().push_values(values);
+    }
+}
+
+impl<T: CircomInputValue> CircomInputValue for [T] {
+    fn push_values(&self, values: &mut Vec<BigInt>) {
+        for item in self {
+            item.push_values(values);
+        }
+    }
+}
+
+impl<T: CircomInputValue> CircomInputValue for Vec<T> {
+    fn push_values(&self, values: &mut Vec<BigInt>) {
+        self.as_slice().push_values(values);
+    }
+}
+
+impl<T: CircomInputValue + ?Sized> CircomInputValue for &T {
+    fn push_values(&self, values: &mut Vec<BigInt>) {
+        (**self).push_values(values);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    #[test]
+    fn values() {
+        assert_eq!(5u8.to_values(), vec![BigInt::from(5)]);
+        assert_eq!((-5i64).to_values(), vec![BigInt::from(-5)]);
+        assert_eq!(true.to_values(), vec![BigInt::from(1)]);
+        assert_eq!(Fr::from(7u64).to_values(), vec![BigInt::from(7)]);
+        assert_eq!(
+            U256::from(u128::MAX).to_values(),
+            vec![BigInt::from(u128::MAX)]
+        );
+        assert_eq!(
+            (-Fr::from(1u64)).to_values(),
+            vec![BigInt::parse_bytes(
+                b"21888242871839275222246405745257275088548364400416034343698204186575808495616",
+                10
+            )
+            .unwrap()]
+        );
+    }
+
+    #[test]
+    fn nested_arrays() {
+        let values = [[1u32, 2, 3], [4, 5, 6]].to_values();
+        assert_eq!(values, (1..=6).map(BigInt::from).collect::<Vec<_>>());
+
+        let values = vec![vec![Fr::from(1u64)], vec![Fr::from(2u64)]].to_values();
+        assert_eq!(values, vec![BigInt::from(1), BigInt::from(2)]);
+    }
+}
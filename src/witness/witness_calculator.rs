@@ -1,5 +1,5 @@
-use super::{fnv, CircomBase, SafeMemory, Wasm};
-use color_eyre::Result;
+use super::{fnv, parse_inputs_json, CircomBase, InputPolicy, SafeMemory, Wasm};
+use color_eyre::{eyre::eyre, Result};
 use num_bigint::BigInt;
 use num_traits::Zero;
 use std::{cell::Cell, ffi::OsStr};
@@ -10,7 +10,7 @@ use wasmer_engine_dylib::Dylib;
 use num::ToPrimitive;
 
 #[cfg(feature = "circom-2")]
-use super::Circom2;
+use super::{Circom2, InputError, WrongSize};
 
 use super::Circom;
 
@@ -20,6 +20,8 @@ pub struct WitnessCalculator {
     pub memory: SafeMemory,
     pub n64: u32,
     pub circom_version: u32,
+    /// How inputs outside of `[0, p)` are handled, the same way for every runtime
+    pub input_policy: InputPolicy,
 }
 
 // Error type to signal end of execution.
@@ -96,6 +98,9 @@ impl WitnessCalculator {
         // Circom 2 feature flag with version 2
         #[cfg(feature = "circom-2")]
         fn new_circom2(instance: Wasm, memory: Memory, version: u32) -> Result<WitnessCalculator> {
+            // Circom 2 modules export their own memory, which is the one holding the
+            // shared RW buffer we read the witness from
+            let memory = instance.memory().unwrap_or(memory);
             let n32 = instance.get_field_num_len32()?;
             let mut safe_memory = SafeMemory::new(memory, n32 as usize, BigInt::zero());
             instance.get_raw_prime()?;
@@ -114,6 +119,7 @@ impl WitnessCalculator {
                 memory: safe_memory,
                 n64,
                 circom_version: version,
+                input_policy: InputPolicy::default(),
             })
         }
 
@@ -132,6 +138,7 @@ impl WitnessCalculator {
                 memory: safe_memory,
                 n64,
                 circom_version: version,
+                input_policy: InputPolicy::default(),
             })
         }
 
@@ -155,6 +162,15 @@ impl WitnessCalculator {
         }
     }
 
+    /// Instantiates the compiled module of the calculator again, e.g. to compute witnesses
+    /// concurrently. Unlike a clone, which shares the WASM instance and its memory, the new
+    /// calculator has its own. The input policy is kept.
+    pub fn instantiate(&self) -> Result<Self> {
+        let mut calculator = Self::from_module(self.instance.module().clone())?;
+        calculator.input_policy = self.input_policy;
+        Ok(calculator)
+    }
+
     pub fn calculate_witness<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
         &mut self,
         inputs: I,
@@ -175,6 +191,17 @@ impl WitnessCalculator {
         }
     }
 
+    /// Calculates the witness from inputs in the `input.json` format accepted by snarkjs,
+    /// see [`crate::parse_inputs_json`]
+    pub fn calculate_witness_json(
+        &mut self,
+        json: &str,
+        sanity_check: bool,
+    ) -> Result<Vec<BigInt>> {
+        let inputs = parse_inputs_json(json)?;
+        self.calculate_witness(inputs, sanity_check)
+    }
+
     // Circom 1 default behavior
     fn calculate_witness_circom1<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
         &mut self,
@@ -184,6 +211,52 @@ impl WitnessCalculator {
         self.instance.init(sanity_check)?;
 
         let old_mem_free_pos = self.memory.free_pos();
+        self.set_inputs_circom1(inputs)?;
+
+        let mut w = Vec::new();
+
+        let n_vars = self.instance.get_n_vars()?;
+        for i in 0..n_vars {
+            let ptr = self.instance.get_ptr_witness(i)? as usize;
+            let el = self.memory.read_fr(ptr)?;
+            w.push(el);
+        }
+
+        self.memory.set_free_pos(old_mem_free_pos);
+
+        Ok(w)
+    }
+
+    // Circom 1 field element fast path: signals are decoded straight from their in-memory
+    // representation into limbs
+    fn calculate_witness_element_circom1<
+        E: ark_ec::PairingEngine,
+        I: IntoIterator<Item = (String, Vec<BigInt>)>,
+    >(
+        &mut self,
+        inputs: I,
+    ) -> Result<Vec<E::Fr>> {
+        let old_mem_free_pos = self.memory.free_pos();
+        self.set_inputs_circom1(inputs)?;
+
+        let r_inv = self.memory.montgomery_r_inv::<E::Fr>();
+
+        let n_vars = self.instance.get_n_vars()?;
+        let mut w = Vec::with_capacity(n_vars as usize);
+        for i in 0..n_vars {
+            let ptr = self.instance.get_ptr_witness(i)? as usize;
+            w.push(self.memory.read_fr_element(ptr, &r_inv)?);
+        }
+
+        self.memory.set_free_pos(old_mem_free_pos);
+
+        Ok(w)
+    }
+
+    fn set_inputs_circom1<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &mut self,
+        inputs: I,
+    ) -> Result<()> {
         let p_sig_offset = self.memory.alloc_u32();
         let p_fr = self.memory.alloc_fr();
 
@@ -197,24 +270,14 @@ impl WitnessCalculator {
             let sig_offset = self.memory.read_u32(p_sig_offset as usize) as usize;
 
             for (i, value) in values.into_iter().enumerate() {
+                let value = self.normalize_input(&name, &value)?;
                 self.memory.write_fr(p_fr as usize, &value)?;
                 self.instance
                     .set_signal(0, 0, (sig_offset + i) as u32, p_fr as u32)?;
             }
         }
 
-        let mut w = Vec::new();
-
-        let n_vars = self.instance.get_n_vars()?;
-        for i in 0..n_vars {
-            let ptr = self.instance.get_ptr_witness(i)? as usize;
-            let el = self.memory.read_fr(ptr)?;
-            w.push(el);
-        }
-
-        self.memory.set_free_pos(old_mem_free_pos);
-
-        Ok(w)
+        Ok(())
     }
 
     // Circom 2 feature flag with version 2
@@ -227,39 +290,146 @@ impl WitnessCalculator {
         self.instance.init(sanity_check)?;
 
         let n32 = self.instance.get_field_num_len32()?;
+        let shared_rw_memory = self.instance.get_shared_rw_memory_start()? as usize;
 
-        // allocate the inputs
+        self.set_inputs_circom2(inputs, n32 as usize, shared_rw_memory)?;
+
+        let mut w = Vec::new();
+
+        let witness_size = self.instance.get_witness_size()?;
+        for i in 0..witness_size {
+            self.instance.get_witness(i)?;
+            let mut arr = vec![0; n32 as usize];
+            for j in 0..n32 as usize {
+                arr[(n32 as usize) - 1 - j] = self.memory.read_u32(shared_rw_memory + 4 * j);
+            }
+            w.push(from_array32(arr));
+        }
+
+        Ok(w)
+    }
+
+    // Circom 2 field element fast path: a single call per signal to copy it into the
+    // shared RW memory, which is then read straight from linear memory into its limbs
+    #[cfg(feature = "circom-2")]
+    fn calculate_witness_element_circom2<
+        E: ark_ec::PairingEngine,
+        I: IntoIterator<Item = (String, Vec<BigInt>)>,
+    >(
+        &mut self,
+        inputs: I,
+    ) -> Result<Vec<E::Fr>> {
+        let n32 = self.instance.get_field_num_len32()? as usize;
+        let shared_rw_memory = self.instance.get_shared_rw_memory_start()? as usize;
+
+        self.set_inputs_circom2(inputs, n32, shared_rw_memory)?;
+
+        let witness_size = self.instance.get_witness_size()?;
+        let mut w = Vec::with_capacity(witness_size as usize);
+        for i in 0..witness_size {
+            self.instance.get_witness(i)?;
+            w.push(self.memory.read_field::<E::Fr>(shared_rw_memory)?);
+        }
+
+        Ok(w)
+    }
+
+    #[cfg(feature = "circom-2")]
+    fn set_inputs_circom2<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &mut self,
+        inputs: I,
+        n32: usize,
+        shared_rw_memory: usize,
+    ) -> Result<()> {
         for (name, values) in inputs.into_iter() {
             let (msb, lsb) = fnv(&name);
 
             for (i, value) in values.into_iter().enumerate() {
-                let f_arr = to_array32(&value, n32 as usize);
+                let value = self.normalize_input(&name, &value)?;
+                let f_arr = to_array32(&value, n32);
                 for j in 0..n32 {
-                    self.instance.write_shared_rw_memory(
-                        j as u32,
-                        f_arr[(n32 as usize) - 1 - (j as usize)],
-                    )?;
+                    self.memory
+                        .write_u32(shared_rw_memory + 4 * j, f_arr[n32 - 1 - j]);
                 }
                 self.instance
                     .set_input_signal(msb as u32, lsb as u32, i as u32)?;
             }
         }
 
-        let mut w = Vec::new();
+        Ok(())
+    }
 
-        let witness_size = self.instance.get_witness_size()?;
-        for i in 0..witness_size {
-            self.instance.get_witness(i)?;
-            let mut arr = vec![0; n32 as usize];
-            for j in 0..n32 {
-                arr[(n32 as usize) - 1 - (j as usize)] = self.instance.read_shared_rw_memory(j)?;
+    fn normalize_input(&self, name: &str, value: &BigInt) -> Result<BigInt> {
+        self.input_policy
+            .normalize(value, &self.memory.prime)
+            .ok_or_else(|| {
+                eyre!(
+                    "input `{}` is out of range: {} is not smaller than the prime in absolute value",
+                    name,
+                    value
+                )
+            })
+    }
+
+    /// Returns whether the runtime reports the size of its input signals, which is needed
+    /// to validate inputs without the circuit's `.sym` file. Only newer circom 2 runtimes
+    /// export `getInputSignalSize`.
+    pub fn can_validate_inputs(&self) -> bool {
+        cfg!(feature = "circom-2")
+            && self.circom_version == 2
+            && self.instance.has_function("getInputSignalSize")
+    }
+
+    /// Checks the provided `(name, number of values)` pairs against the input signal
+    /// sizes reported by the runtime, returning an [`InputError`] listing every mismatch.
+    /// See [`WitnessCalculator::can_validate_inputs`].
+    #[cfg(feature = "circom-2")]
+    pub fn validate_inputs<'a>(
+        &self,
+        inputs: impl IntoIterator<Item = (&'a str, usize)>,
+    ) -> Result<()> {
+        if !self.can_validate_inputs() {
+            return Err(eyre!("the runtime does not report its input signal sizes"));
+        }
+
+        let mut provided = std::collections::HashMap::new();
+        for (name, len) in inputs {
+            *provided.entry(name).or_insert(0) += len;
+        }
+
+        let mut err = InputError::default();
+        let mut covered = 0;
+        for (name, actual) in provided {
+            let (msb, lsb) = fnv(name);
+            let expected = self.instance.get_input_signal_size(msb, lsb)?;
+            if expected < 0 {
+                err.unknown.push(name.to_string());
+                continue;
+            }
+
+            let expected = expected as usize;
+            covered += expected;
+            if expected != actual {
+                err.wrong_size.push(WrongSize {
+                    name: name.to_string(),
+                    expected,
+                    actual,
+                });
             }
-            w.push(from_array32(arr));
         }
 
-        Ok(w)
+        // the runtime can't list its inputs, but it knows how many values it expects
+        if self.instance.has_function("getInputSize") {
+            let expected = self.instance.get_input_size()? as usize;
+            err.missing_values = expected.saturating_sub(covered);
+        }
+
+        Ok(err.into_result()?)
     }
 
+    /// Calculates the witness and returns it as field elements. Values are read in bulk
+    /// from the WASM linear memory straight into their limbs, skipping the BigInt
+    /// conversion done by [`WitnessCalculator::calculate_witness`].
     pub fn calculate_witness_element<
         E: ark_ec::PairingEngine,
         I: IntoIterator<Item = (String, Vec<BigInt>)>,
@@ -268,26 +438,19 @@ impl WitnessCalculator {
         inputs: I,
         sanity_check: bool,
     ) -> Result<Vec<E::Fr>> {
-        use ark_ff::{FpParameters, PrimeField};
-        let witness = self.calculate_witness(inputs, sanity_check)?;
-        let modulus = <<E::Fr as PrimeField>::Params as FpParameters>::MODULUS;
-
-        // convert it to field elements
-        use num_traits::Signed;
-        let witness = witness
-            .into_iter()
-            .map(|w| {
-                let w = if w.sign() == num_bigint::Sign::Minus {
-                    // Need to negate the witness element if negative
-                    modulus.into() - w.abs().to_biguint().unwrap()
-                } else {
-                    w.to_biguint().unwrap()
-                };
-                E::Fr::from(w)
-            })
-            .collect::<Vec<_>>();
+        self.instance.init(sanity_check)?;
 
-        Ok(witness)
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "circom-2")] {
+                match self.circom_version {
+                    2 => self.calculate_witness_element_circom2::<E, _>(inputs),
+                    1 => self.calculate_witness_element_circom1::<E, _>(inputs),
+                    _ => panic!("Unknown Circom version")
+                }
+            } else {
+                self.calculate_witness_element_circom1::<E, _>(inputs)
+            }
+        }
     }
 
     pub fn get_witness_buffer(&self) -> Result<Vec<u8>> {
@@ -354,7 +517,7 @@ mod runtime {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::{collections::HashMap, path::PathBuf};
+    use std::path::PathBuf;
 
     struct TestCase<'a> {
         circuit_path: &'a str,
@@ -444,15 +607,53 @@ mod tests {
         });
     }
 
-    use serde_json::Value;
     use std::str::FromStr;
 
-    fn value_to_bigint(v: Value) -> BigInt {
-        match v {
-            Value::String(inner) => BigInt::from_str(&inner).unwrap(),
-            Value::Number(inner) => BigInt::from(inner.as_u64().expect("not a u32")),
-            _ => panic!("unsupported type"),
+    fn input_policy_test(circuit_path: &str) {
+        let mut wtns = WitnessCalculator::new(circuit_path).unwrap();
+        let p = wtns.memory.prime.clone();
+        let inputs =
+            |a: BigInt, b: BigInt| vec![("a".to_string(), vec![a]), ("b".to_string(), vec![b])];
+
+        let expected = wtns
+            .calculate_witness(inputs(&p - 3, BigInt::from(11)), false)
+            .unwrap();
+        for (a, b) in [
+            (BigInt::from(-3), BigInt::from(11)),
+            (&p * 2 - 3, &p + 11),
+            (-&p - 3, -&p * 3 + 11),
+        ] {
+            let res = wtns.calculate_witness(inputs(a.clone(), b.clone()), false);
+            assert_eq!(res.unwrap(), expected);
+            let res = wtns.calculate_witness_element::<ark_bn254::Bn254, _>(inputs(a, b), false);
+            assert_eq!(res.unwrap()[1], -ark_bn254::Fr::from(33u64));
         }
+
+        wtns.input_policy = InputPolicy::Strict;
+        let res = wtns
+            .calculate_witness(inputs(BigInt::from(-3), &p - 1), false)
+            .unwrap();
+        assert_eq!(res[1], BigInt::from(3));
+        assert!(wtns
+            .calculate_witness(inputs(BigInt::from(3), p.clone()), false)
+            .is_err());
+        assert!(wtns
+            .calculate_witness_element::<ark_bn254::Bn254, _>(
+                inputs(-&p - 3, BigInt::from(11)),
+                false
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn input_policy() {
+        input_policy_test(&root_path("test-vectors/mycircuit.wasm"));
+    }
+
+    #[cfg(feature = "circom-2")]
+    #[test]
+    fn input_policy_circom2() {
+        input_policy_test(&root_path("test-vectors/circom2_multiplier2.wasm"));
     }
 
     fn run_test(case: TestCase) {
@@ -465,30 +666,19 @@ mod tests {
         assert_eq!(wtns.n64 as u32, case.n64);
 
         let inputs_str = std::fs::read_to_string(case.inputs_path).unwrap();
-        let inputs: std::collections::HashMap<String, serde_json::Value> =
-            serde_json::from_str(&inputs_str).unwrap();
-
-        let inputs = inputs
-            .iter()
-            .map(|(key, value)| {
-                let res = match value {
-                    Value::String(inner) => {
-                        vec![BigInt::from_str(inner).unwrap()]
-                    }
-                    Value::Number(inner) => {
-                        vec![BigInt::from(inner.as_u64().expect("not a u32"))]
-                    }
-                    Value::Array(inner) => inner.iter().cloned().map(value_to_bigint).collect(),
-                    _ => panic!(),
-                };
-
-                (key.clone(), res)
-            })
-            .collect::<HashMap<_, _>>();
+        let inputs = parse_inputs_json(&inputs_str).unwrap();
 
-        let res = wtns.calculate_witness(inputs, false).unwrap();
+        let res = wtns.calculate_witness_json(&inputs_str, false).unwrap();
         for (r, w) in res.iter().zip(case.witness) {
             assert_eq!(r, &BigInt::from_str(w).unwrap());
         }
+
+        let res = wtns
+            .calculate_witness_element::<ark_bn254::Bn254, _>(inputs, false)
+            .unwrap();
+        assert_eq!(res.len(), case.n_vars as usize);
+        for (r, w) in res.iter().zip(case.witness) {
+            assert_eq!(r, &ark_bn254::Fr::from_str(w).unwrap());
+        }
     }
 }
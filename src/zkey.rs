@@ -25,7 +25,7 @@
 //!  PointsC(8)
 //!  PointsH(9)
 //!  Contributions(10)
-use ark_ff::{BigInteger256, FromBytes, PrimeField};
+use ark_ff::{BigInteger256, FpParameters, FromBytes, PrimeField};
 use ark_relations::r1cs::ConstraintMatrices;
 use ark_serialize::{CanonicalDeserialize, SerializationError};
 use ark_std::log2;
@@ -33,30 +33,178 @@ use byteorder::{LittleEndian, ReadBytesExt};
 
 use std::{
     collections::HashMap,
-    io::{Read, Result as IoResult, Seek, SeekFrom},
+    io::{Error, ErrorKind, Read, Seek, SeekFrom},
 };
 
-use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_bn254::{Bn254, Fq, Fq2, FqParameters, Fr, FrParameters, G1Affine, G2Affine};
 use ark_groth16::{ProvingKey, VerifyingKey};
 use num_traits::Zero;
 
+use ark_ec::{models::SWModelParameters, short_weierstrass_jacobian::GroupAffine};
+use rayon::prelude::*;
+
+use crate::validation::{InvalidPoint, Validation};
+
+mod lazy;
+pub use lazy::Zkey;
+
+mod cache;
+pub use cache::{
+    hash_zkey, read_zkey_cache, read_zkey_cached, write_zkey_cache, Compression, ZkeyHash,
+};
+
+mod audit;
+pub use audit::{audit_proving_key, audit_zkey, AuditReport, Issue, KeyPoint, Severity};
+
+/// Error returned when reading a malformed zkey file
+#[derive(thiserror::Error, Debug)]
+pub enum ZkeyError {
+    #[error(transparent)]
+    Io(#[from] Error),
+    #[error("invalid magic number {0:?}, expected \"zkey\"")]
+    InvalidMagic([u8; 4]),
+    #[error("unsupported version {0}")]
+    UnsupportedVersion(u32),
+    #[error("section {section} of {size} bytes at offset {offset} exceeds the file length of {file_len} bytes")]
+    SectionOutOfBounds {
+        section: u32,
+        offset: u64,
+        size: u64,
+        file_len: u64,
+    },
+    #[error("no section {0} found")]
+    MissingSection(u32),
+    #[error("invalid size of section {section}: {size} bytes, expected {expected}")]
+    SectionSize {
+        section: u32,
+        size: u64,
+        expected: u64,
+    },
+    #[error("unsupported prover type {0}, only Groth16 (1) is supported")]
+    UnsupportedProver(u32),
+    #[error("unsupported curve, only bn254 is supported")]
+    UnsupportedCurve,
+    #[error("invalid header: {0}")]
+    InvalidHeader(&'static str),
+    #[error("coefficient {index} (matrix {matrix}, constraint {constraint}, signal {signal}) is out of range")]
+    InvalidCoefficient {
+        index: u32,
+        matrix: u32,
+        constraint: u32,
+        signal: u32,
+    },
+    #[error("field element is not reduced modulo the field's prime")]
+    InvalidFieldElement,
+    #[error("invalid point in section {section}: {source}")]
+    InvalidPoint { section: u32, source: InvalidPoint },
+    #[error("not a zkey cache")]
+    InvalidCache,
+    #[error(transparent)]
+    Serialization(#[from] SerializationError),
+}
+
+impl From<ZkeyError> for Error {
+    fn from(err: ZkeyError) -> Self {
+        match err {
+            ZkeyError::Io(err) => err,
+            err => Error::new(ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+const FIELD_SIZE: u64 = 32;
+const G1_SIZE: u64 = 2 * FIELD_SIZE;
+const G2_SIZE: u64 = 4 * FIELD_SIZE;
+// matrix, constraint and signal followed by the coefficient
+const COEFF_SIZE: u64 = 12 + FIELD_SIZE;
+const GROTH16: u32 = 1;
+
 #[derive(Clone, Debug)]
 struct Section {
     position: u64,
-    #[allow(dead_code)]
-    size: usize,
+    size: u64,
 }
 
 /// Reads a SnarkJS ZKey file into an Arkworks ProvingKey.
+///
+/// The file is validated while reading, so that truncated or malicious files return an
+/// error instead of panicking, and no allocation is larger than the section it is read from.
+///
+/// The points of the verifying key are fully validated, while the proving key's queries are
+/// only checked to be on the curve, see [`read_zkey_with_validation`]. The point at infinity,
+/// which snarkjs encodes as `(0, 0)`, is decoded as `G1Affine::zero()` or `G2Affine::zero()`.
 pub fn read_zkey<R: Read + Seek>(
     reader: &mut R,
-) -> IoResult<(ProvingKey<Bn254>, ConstraintMatrices<Fr>)> {
+) -> Result<(ProvingKey<Bn254>, ConstraintMatrices<Fr>), ZkeyError> {
+    read_zkey_with_validation(reader, Validation::OnCurve)
+}
+
+/// Reads a SnarkJS ZKey file, checking the points of the proving key's queries according to
+/// `validation`.
+///
+/// Invalid points in the queries can only produce invalid proofs, so skipping the subgroup
+/// checks of the large B G2 query is usually safe. The verifying key's points are always
+/// fully validated, as invalid G2 points there break the soundness of verification, unless
+/// `validation` is [`Validation::Unchecked`], which is meant for trusted zkeys.
+pub fn read_zkey_with_validation<R: Read + Seek>(
+    reader: &mut R,
+    validation: Validation,
+) -> Result<(ProvingKey<Bn254>, ConstraintMatrices<Fr>), ZkeyError> {
     let mut binfile = BinFile::new(reader)?;
-    let proving_key = binfile.proving_key()?;
-    let matrices = binfile.matrices()?;
+    binfile.validation = validation;
+    let header = binfile.groth_header()?;
+    let proving_key = binfile.proving_key(&header)?;
+    let matrices = binfile.matrices(&header)?;
     Ok((proving_key, matrices))
 }
 
+/// Hash identifying the circuit a zkey was set up for
+///
+/// snarkjs computes it over the initial parameters of the setup, derived from both the r1cs
+/// and the powers of tau, and prints it in `zkey new` and `zkey verify`. It is kept by every
+/// contribution, so it is the same for all the zkeys of a ceremony.
+pub type CircuitHash = [u8; 64];
+
+/// Metadata of a zkey, read from its header without decoding the proving key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ZkeyHeader {
+    /// Size in bytes of the elements of the base field
+    pub n8q: u32,
+    /// Size in bytes of the elements of the scalar field
+    pub n8r: u32,
+    pub n_vars: usize,
+    pub n_public: usize,
+    pub domain_size: usize,
+    pub circuit_hash: CircuitHash,
+    /// Number of contributions to the phase 2 ceremony
+    pub num_contributions: u32,
+}
+
+/// Reads the header of a zkey, seeking over every section but the headers and the
+/// contributions
+pub fn read_zkey_header<R: Read + Seek>(reader: &mut R) -> Result<ZkeyHeader, ZkeyError> {
+    let mut binfile = BinFile::new(reader)?;
+    let header = binfile.groth_header()?;
+    binfile.zkey_header(&header)
+}
+
+/// Reads the fully validated verifying key of a zkey and its header, without decoding the
+/// proving key
+pub fn read_zkey_vk<R: Read + Seek>(
+    reader: &mut R,
+) -> Result<(VerifyingKey<Bn254>, ZkeyHeader), ZkeyError> {
+    let mut binfile = BinFile::new(reader)?;
+    let header = binfile.groth_header()?;
+    let vk = VerifyingKey {
+        alpha_g1: header.verifying_key.alpha_g1,
+        beta_g2: header.verifying_key.beta_g2,
+        gamma_g2: header.verifying_key.gamma_g2,
+        delta_g2: header.verifying_key.delta_g2,
+        gamma_abc_g1: binfile.ic(header.n_public)?,
+    };
+    Ok((vk, binfile.zkey_header(&header)?))
+}
+
 #[derive(Debug)]
 struct BinFile<'a, R> {
     #[allow(dead_code)]
@@ -65,14 +213,26 @@ struct BinFile<'a, R> {
     version: u32,
     sections: HashMap<u32, Vec<Section>>,
     reader: &'a mut R,
+    // validation of the points of the queries
+    validation: Validation,
 }
 
 impl<'a, R: Read + Seek> BinFile<'a, R> {
-    fn new(reader: &'a mut R) -> IoResult<Self> {
+    fn new(reader: &'a mut R) -> Result<Self, ZkeyError> {
+        let start = reader.stream_position()?;
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(start))?;
+
         let mut magic = [0u8; 4];
         reader.read_exact(&mut magic)?;
+        if &magic != b"zkey" {
+            return Err(ZkeyError::InvalidMagic(magic));
+        }
 
         let version = reader.read_u32::<LittleEndian>()?;
+        if version != 1 {
+            return Err(ZkeyError::UnsupportedVersion(version));
+        }
 
         let num_sections = reader.read_u32::<LittleEndian>()?;
 
@@ -80,26 +240,46 @@ impl<'a, R: Read + Seek> BinFile<'a, R> {
         for _ in 0..num_sections {
             let section_id = reader.read_u32::<LittleEndian>()?;
             let section_length = reader.read_u64::<LittleEndian>()?;
+            let position = reader.stream_position()?;
+            if section_length > file_len.saturating_sub(position) {
+                return Err(ZkeyError::SectionOutOfBounds {
+                    section: section_id,
+                    offset: position,
+                    size: section_length,
+                    file_len,
+                });
+            }
 
             let section = sections.entry(section_id).or_insert_with(Vec::new);
             section.push(Section {
-                position: reader.stream_position()?,
-                size: section_length as usize,
+                position,
+                size: section_length,
             });
 
-            reader.seek(SeekFrom::Current(section_length as i64))?;
+            reader.seek(SeekFrom::Start(position + section_length))?;
         }
 
-        Ok(Self {
-            ftype: std::str::from_utf8(&magic[..]).unwrap().to_string(),
+        let mut binfile = Self {
+            ftype: "zkey".to_string(),
             version,
             sections,
             reader,
-        })
+            validation: Validation::Full,
+        };
+        binfile.check_prover()?;
+        Ok(binfile)
+    }
+
+    fn check_prover(&mut self) -> Result<(), ZkeyError> {
+        let section = self.get_section_sized(1, 4)?;
+        self.reader.seek(SeekFrom::Start(section.position))?;
+        match self.reader.read_u32::<LittleEndian>()? {
+            GROTH16 => Ok(()),
+            prover => Err(ZkeyError::UnsupportedProver(prover)),
+        }
     }
 
-    fn proving_key(&mut self) -> IoResult<ProvingKey<Bn254>> {
-        let header = self.groth_header()?;
+    fn proving_key(&mut self, header: &HeaderGroth) -> Result<ProvingKey<Bn254>, ZkeyError> {
         let ic = self.ic(header.n_public)?;
 
         let a_query = self.a_query(header.n_vars)?;
@@ -130,100 +310,239 @@ impl<'a, R: Read + Seek> BinFile<'a, R> {
         Ok(pk)
     }
 
-    fn get_section(&self, id: u32) -> Section {
-        self.sections.get(&id).unwrap()[0].clone()
+    fn zkey_header(&mut self, header: &HeaderGroth) -> Result<ZkeyHeader, ZkeyError> {
+        // the contributions section starts with the 64 bytes of the circuit's hash
+        let section = self.get_section(10)?;
+        if section.size < 64 + 4 {
+            return Err(ZkeyError::InvalidHeader(
+                "the contributions section is too short",
+            ));
+        }
+        self.reader.seek(SeekFrom::Start(section.position))?;
+        let mut circuit_hash = [0; 64];
+        self.reader.read_exact(&mut circuit_hash)?;
+        let num_contributions = self.reader.read_u32::<LittleEndian>()?;
+
+        Ok(ZkeyHeader {
+            n8q: header.n8q,
+            n8r: header.n8r,
+            n_vars: header.n_vars,
+            n_public: header.n_public,
+            domain_size: header.domain_size as usize,
+            circuit_hash,
+            num_contributions,
+        })
+    }
+
+    fn get_section(&self, id: u32) -> Result<Section, ZkeyError> {
+        self.sections
+            .get(&id)
+            .and_then(|sections| sections.first())
+            .cloned()
+            .ok_or(ZkeyError::MissingSection(id))
     }
 
-    fn groth_header(&mut self) -> IoResult<HeaderGroth> {
-        let section = self.get_section(2);
+    /// Returns the section, checking that it is `size` bytes long
+    fn get_section_sized(&self, id: u32, size: u64) -> Result<Section, ZkeyError> {
+        let section = self.get_section(id)?;
+        if section.size != size {
+            return Err(ZkeyError::SectionSize {
+                section: id,
+                size: section.size,
+                expected: size,
+            });
+        }
+        Ok(section)
+    }
+
+    fn vk_validation(&self) -> Validation {
+        match self.validation {
+            Validation::Unchecked => Validation::Unchecked,
+            _ => Validation::Full,
+        }
+    }
+
+    fn groth_header(&mut self) -> Result<HeaderGroth, ZkeyError> {
+        let section = self.get_section_sized(2, HeaderGroth::SIZE)?;
         let header = HeaderGroth::new(&mut self.reader, &section)?;
+        // bounds the allocations depending on the domain size by the size of the file
+        self.get_section_sized(9, header.domain_size as u64 * G1_SIZE)?;
+
+        let vk = &header.verifying_key;
+        let validation = self.vk_validation();
+        validation
+            .check(&[vk.alpha_g1, vk.beta_g1, vk.delta_g1])
+            .and_then(|_| validation.check(&[vk.beta_g2, vk.gamma_g2, vk.delta_g2]))
+            .map_err(|source| ZkeyError::InvalidPoint { section: 2, source })?;
         Ok(header)
     }
 
-    fn ic(&mut self, n_public: usize) -> IoResult<Vec<G1Affine>> {
+    fn ic(&mut self, n_public: usize) -> Result<Vec<G1Affine>, ZkeyError> {
         // the range is non-inclusive so we do +1 to get all inputs
-        self.g1_section(n_public + 1, 3)
+        self.g1_section(n_public + 1, 3, self.vk_validation())
     }
 
     /// Returns the [`ConstraintMatrices`] corresponding to the zkey
-    pub fn matrices(&mut self) -> IoResult<ConstraintMatrices<Fr>> {
-        let header = self.groth_header()?;
-
-        let section = self.get_section(4);
-        self.reader.seek(SeekFrom::Start(section.position))?;
-        let num_coeffs: u32 = self.reader.read_u32::<LittleEndian>()?;
-
-        // insantiate AB
-        let mut matrices = vec![vec![vec![]; header.domain_size as usize]; 2];
-        let mut max_constraint_index = 0;
-        for _ in 0..num_coeffs {
-            let matrix: u32 = self.reader.read_u32::<LittleEndian>()?;
-            let constraint: u32 = self.reader.read_u32::<LittleEndian>()?;
-            let signal: u32 = self.reader.read_u32::<LittleEndian>()?;
-
-            let value: Fr = deserialize_field_fr(&mut self.reader)?;
-            max_constraint_index = std::cmp::max(max_constraint_index, constraint);
-            matrices[matrix as usize][constraint as usize].push((value, signal as usize));
-        }
-
-        let num_constraints = max_constraint_index as usize - header.n_public;
-        // Remove the public input constraints, Arkworks adds them later
-        matrices.iter_mut().for_each(|m| {
-            m.truncate(num_constraints);
-        });
-        // This is taken from Arkworks' to_matrices() function
-        let a = matrices[0].clone();
-        let b = matrices[1].clone();
-        let a_num_non_zero: usize = a.iter().map(|lc| lc.len()).sum();
-        let b_num_non_zero: usize = b.iter().map(|lc| lc.len()).sum();
-        let matrices = ConstraintMatrices {
-            num_instance_variables: header.n_public + 1,
-            num_witness_variables: header.n_vars - header.n_public,
-            num_constraints,
-
-            a_num_non_zero,
-            b_num_non_zero,
-            c_num_non_zero: 0,
+    fn matrices(&mut self, header: &HeaderGroth) -> Result<ConstraintMatrices<Fr>, ZkeyError> {
+        let size = self.get_section(4)?.size;
+        let buf = self.read_section(4, size)?;
+        decode_matrices(&buf, header)
+    }
 
-            a,
-            b,
-            c: vec![],
-        };
+    fn a_query(&mut self, n_vars: usize) -> Result<Vec<G1Affine>, ZkeyError> {
+        self.g1_section(n_vars, 5, self.validation)
+    }
 
-        Ok(matrices)
+    fn b_g1_query(&mut self, n_vars: usize) -> Result<Vec<G1Affine>, ZkeyError> {
+        self.g1_section(n_vars, 6, self.validation)
     }
 
-    fn a_query(&mut self, n_vars: usize) -> IoResult<Vec<G1Affine>> {
-        self.g1_section(n_vars, 5)
+    fn b_g2_query(&mut self, n_vars: usize) -> Result<Vec<G2Affine>, ZkeyError> {
+        self.g2_section(n_vars, 7, self.validation)
     }
 
-    fn b_g1_query(&mut self, n_vars: usize) -> IoResult<Vec<G1Affine>> {
-        self.g1_section(n_vars, 6)
+    fn l_query(&mut self, n_vars: usize) -> Result<Vec<G1Affine>, ZkeyError> {
+        self.g1_section(n_vars, 8, self.validation)
     }
 
-    fn b_g2_query(&mut self, n_vars: usize) -> IoResult<Vec<G2Affine>> {
-        self.g2_section(n_vars, 7)
+    fn h_query(&mut self, n_vars: usize) -> Result<Vec<G1Affine>, ZkeyError> {
+        self.g1_section(n_vars, 9, self.validation)
     }
 
-    fn l_query(&mut self, n_vars: usize) -> IoResult<Vec<G1Affine>> {
-        self.g1_section(n_vars, 8)
+    fn g1_section(
+        &mut self,
+        num: usize,
+        section_id: usize,
+        validation: Validation,
+    ) -> Result<Vec<G1Affine>, ZkeyError> {
+        let buf = self.read_section(section_id as u32, num as u64 * G1_SIZE)?;
+        let points = deserialize_g1_vec(&buf)?;
+        check_points(&points, section_id, 0, validation)?;
+        Ok(points)
     }
 
-    fn h_query(&mut self, n_vars: usize) -> IoResult<Vec<G1Affine>> {
-        self.g1_section(n_vars, 9)
+    fn g2_section(
+        &mut self,
+        num: usize,
+        section_id: usize,
+        validation: Validation,
+    ) -> Result<Vec<G2Affine>, ZkeyError> {
+        let buf = self.read_section(section_id as u32, num as u64 * G2_SIZE)?;
+        let points = deserialize_g2_vec(&buf)?;
+        check_points(&points, section_id, 0, validation)?;
+        Ok(points)
     }
 
-    fn g1_section(&mut self, num: usize, section_id: usize) -> IoResult<Vec<G1Affine>> {
-        let section = self.get_section(section_id as u32);
+    /// Reads the whole section, checking that it is `size` bytes long
+    fn read_section(&mut self, id: u32, size: u64) -> Result<Vec<u8>, ZkeyError> {
+        let section = self.get_section_sized(id, size)?;
         self.reader.seek(SeekFrom::Start(section.position))?;
-        deserialize_g1_vec(self.reader, num as u32)
+        let mut buf = vec![0; size as usize];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf)
     }
+}
 
-    fn g2_section(&mut self, num: usize, section_id: usize) -> IoResult<Vec<G2Affine>> {
-        let section = self.get_section(section_id as u32);
-        self.reader.seek(SeekFrom::Start(section.position))?;
-        deserialize_g2_vec(self.reader, num as u32)
+// Number of coefficients decoded in parallel before being sorted into the rows, which
+// bounds the memory they take on top of the matrices
+const COEFFS_PER_CHUNK: usize = 1 << 16;
+
+/// Decodes the Coefs section into the A and B matrices
+fn decode_matrices(buf: &[u8], header: &HeaderGroth) -> Result<ConstraintMatrices<Fr>, ZkeyError> {
+    decode_matrices_by(buf, header, COEFFS_PER_CHUNK)
+}
+
+fn decode_matrices_by(
+    buf: &[u8],
+    header: &HeaderGroth,
+    coeffs_per_chunk: usize,
+) -> Result<ConstraintMatrices<Fr>, ZkeyError> {
+    num_coefficients(buf)?;
+
+    // the domain size is bounded by the size of the H query, see `BinFile::groth_header`
+    let mut a = vec![vec![]; header.domain_size as usize];
+    let mut b = vec![vec![]; header.domain_size as usize];
+    let mut max_constraint_index = 0;
+    let chunk_size = coeffs_per_chunk * COEFF_SIZE as usize;
+    for (i, chunk) in buf[4..].chunks(chunk_size).enumerate() {
+        let coeffs = decode_coefficients(chunk, i * coeffs_per_chunk, header)?;
+        for (matrix, constraint, signal, value) in coeffs {
+            max_constraint_index = std::cmp::max(max_constraint_index, constraint);
+            let m = if matrix == 0 { &mut a } else { &mut b };
+            m[constraint as usize].push((value, signal as usize));
+        }
+    }
+
+    // the constraints of the public inputs are last
+    let num_constraints = (max_constraint_index as usize)
+        .checked_sub(header.n_public)
+        .ok_or(ZkeyError::InvalidHeader(
+            "the coefficients do not cover the public inputs",
+        ))?;
+    // Remove the public input constraints, Arkworks adds them later
+    a.truncate(num_constraints);
+    b.truncate(num_constraints);
+    // This is taken from Arkworks' to_matrices() function
+    let a_num_non_zero: usize = a.iter().map(|lc| lc.len()).sum();
+    let b_num_non_zero: usize = b.iter().map(|lc| lc.len()).sum();
+    let matrices = ConstraintMatrices {
+        num_instance_variables: header.n_public + 1,
+        num_witness_variables: header.n_vars - header.n_public,
+        num_constraints,
+
+        a_num_non_zero,
+        b_num_non_zero,
+        c_num_non_zero: 0,
+
+        a,
+        b,
+        c: vec![],
+    };
+
+    Ok(matrices)
+}
+
+// matrix, constraint and signal of a coefficient, and its value
+type Coefficient = (u32, u32, u32, Fr);
+
+/// Reads the number of coefficients of the Coefs section `buf`, checking the section's size
+fn num_coefficients(buf: &[u8]) -> Result<usize, ZkeyError> {
+    let num_coeffs = (&buf[..]).read_u32::<LittleEndian>()?;
+    let expected = 4 + num_coeffs as u64 * COEFF_SIZE;
+    if buf.len() as u64 != expected {
+        return Err(ZkeyError::SectionSize {
+            section: 4,
+            size: buf.len() as u64,
+            expected,
+        });
     }
+    Ok(num_coeffs as usize)
+}
+
+/// Decodes the coefficients in `buf` in parallel, the first one being coefficient `first` of
+/// the section
+fn decode_coefficients(
+    buf: &[u8],
+    first: usize,
+    header: &HeaderGroth,
+) -> Result<Vec<Coefficient>, ZkeyError> {
+    buf.par_chunks_exact(COEFF_SIZE as usize)
+        .enumerate()
+        .map(|(index, mut buf)| {
+            let matrix = buf.read_u32::<LittleEndian>()?;
+            let constraint = buf.read_u32::<LittleEndian>()?;
+            let signal = buf.read_u32::<LittleEndian>()?;
+            if matrix > 1 || constraint >= header.domain_size || signal as usize >= header.n_vars {
+                return Err(ZkeyError::InvalidCoefficient {
+                    index: (first + index) as u32,
+                    matrix,
+                    constraint,
+                    signal,
+                });
+            }
+            Ok((matrix, constraint, signal, deserialize_field_fr(&mut buf)?))
+        })
+        .collect()
 }
 
 #[derive(Default, Clone, Debug, CanonicalDeserialize)]
@@ -237,7 +556,7 @@ pub struct ZVerifyingKey {
 }
 
 impl ZVerifyingKey {
-    fn new<R: Read>(reader: &mut R) -> IoResult<Self> {
+    fn new<R: Read>(reader: &mut R) -> Result<Self, ZkeyError> {
         let alpha_g1 = deserialize_g1(reader)?;
         let beta_g1 = deserialize_g1(reader)?;
         let beta_g2 = deserialize_g2(reader)?;
@@ -258,11 +577,9 @@ impl ZVerifyingKey {
 
 #[derive(Clone, Debug)]
 struct HeaderGroth {
-    #[allow(dead_code)]
     n8q: u32,
     #[allow(dead_code)]
     q: BigInteger256,
-    #[allow(dead_code)]
     n8r: u32,
     #[allow(dead_code)]
     r: BigInteger256,
@@ -278,12 +595,15 @@ struct HeaderGroth {
 }
 
 impl HeaderGroth {
-    fn new<R: Read + Seek>(reader: &mut R, section: &Section) -> IoResult<Self> {
+    // n8q, q, n8r, r, n_vars, n_public, domain_size and 3 G1 and 3 G2 points
+    const SIZE: u64 = 4 + FIELD_SIZE + 4 + FIELD_SIZE + 3 * 4 + 3 * G1_SIZE + 3 * G2_SIZE;
+
+    fn new<R: Read + Seek>(reader: &mut R, section: &Section) -> Result<Self, ZkeyError> {
         reader.seek(SeekFrom::Start(section.position))?;
         Self::read(reader)
     }
 
-    fn read<R: Read>(mut reader: &mut R) -> IoResult<Self> {
+    fn read<R: Read>(mut reader: &mut R) -> Result<Self, ZkeyError> {
         // TODO: Impl From<u32> in Arkworks
         let n8q: u32 = FromBytes::read(&mut reader)?;
         // group order r of Bn254
@@ -293,10 +613,28 @@ impl HeaderGroth {
         // Prime field modulus
         let r = BigInteger256::read(&mut reader)?;
 
+        if n8q as u64 != FIELD_SIZE
+            || n8r as u64 != FIELD_SIZE
+            || q != FqParameters::MODULUS
+            || r != FrParameters::MODULUS
+        {
+            return Err(ZkeyError::UnsupportedCurve);
+        }
+
         let n_vars = u32::read(&mut reader)? as usize;
         let n_public = u32::read(&mut reader)? as usize;
+        if n_public >= n_vars {
+            return Err(ZkeyError::InvalidHeader(
+                "there are more public inputs than variables",
+            ));
+        }
 
         let domain_size: u32 = FromBytes::read(&mut reader)?;
+        if !domain_size.is_power_of_two() {
+            return Err(ZkeyError::InvalidHeader(
+                "the domain size is not a power of two",
+            ));
+        }
         let power = log2(domain_size as usize);
 
         let verifying_key = ZVerifyingKey::new(&mut reader)?;
@@ -317,44 +655,75 @@ impl HeaderGroth {
 
 // need to divide by R, since snarkjs outputs the zkey with coefficients
 // multiplieid by R^2
-fn deserialize_field_fr<R: Read>(reader: &mut R) -> IoResult<Fr> {
+fn deserialize_field_fr<R: Read>(reader: &mut R) -> Result<Fr, ZkeyError> {
     let bigint = BigInteger256::read(reader)?;
+    if bigint >= FrParameters::MODULUS {
+        return Err(ZkeyError::InvalidFieldElement);
+    }
     Ok(Fr::new(Fr::new(bigint).into_repr()))
 }
 
 // skips the multiplication by R because Circom points are already in Montgomery form
-fn deserialize_field<R: Read>(reader: &mut R) -> IoResult<Fq> {
+fn deserialize_field<R: Read>(reader: &mut R) -> Result<Fq, ZkeyError> {
     let bigint = BigInteger256::read(reader)?;
+    if bigint >= FqParameters::MODULUS {
+        return Err(ZkeyError::InvalidFieldElement);
+    }
     // if you use ark_ff::PrimeField::from_repr it multiplies by R
     Ok(Fq::new(bigint))
 }
 
-pub fn deserialize_field2<R: Read>(reader: &mut R) -> IoResult<Fq2> {
+pub fn deserialize_field2<R: Read>(reader: &mut R) -> Result<Fq2, ZkeyError> {
     let c0 = deserialize_field(reader)?;
     let c1 = deserialize_field(reader)?;
     Ok(Fq2::new(c0, c1))
 }
 
-fn deserialize_g1<R: Read>(reader: &mut R) -> IoResult<G1Affine> {
+fn deserialize_g1<R: Read>(reader: &mut R) -> Result<G1Affine, ZkeyError> {
     let x = deserialize_field(reader)?;
     let y = deserialize_field(reader)?;
-    let infinity = x.is_zero() && y.is_zero();
-    Ok(G1Affine::new(x, y, infinity))
+    // snarkjs encodes the point at infinity as (0, 0)
+    if x.is_zero() && y.is_zero() {
+        return Ok(G1Affine::zero());
+    }
+    Ok(G1Affine::new(x, y, false))
 }
 
-fn deserialize_g2<R: Read>(reader: &mut R) -> IoResult<G2Affine> {
+fn deserialize_g2<R: Read>(reader: &mut R) -> Result<G2Affine, ZkeyError> {
     let f1 = deserialize_field2(reader)?;
     let f2 = deserialize_field2(reader)?;
-    let infinity = f1.is_zero() && f2.is_zero();
-    Ok(G2Affine::new(f1, f2, infinity))
+    // same as in `deserialize_g1`
+    if f1.is_zero() && f2.is_zero() {
+        return Ok(G2Affine::zero());
+    }
+    Ok(G2Affine::new(f1, f2, false))
+}
+
+fn check_points<P: SWModelParameters>(
+    points: &[GroupAffine<P>],
+    section_id: usize,
+    first: usize,
+    validation: Validation,
+) -> Result<(), ZkeyError> {
+    validation
+        .check_from(points, first)
+        .map_err(|source| ZkeyError::InvalidPoint {
+            section: section_id as u32,
+            source,
+        })
 }
 
-fn deserialize_g1_vec<R: Read>(reader: &mut R, n_vars: u32) -> IoResult<Vec<G1Affine>> {
-    (0..n_vars).map(|_| deserialize_g1(reader)).collect()
+// points are decoded in parallel from a buffer holding the whole section
+fn deserialize_g1_vec(buf: &[u8]) -> Result<Vec<G1Affine>, ZkeyError> {
+    buf.par_chunks_exact(G1_SIZE as usize)
+        .map(|mut buf| deserialize_g1(&mut buf))
+        .collect()
 }
 
-fn deserialize_g2_vec<R: Read>(reader: &mut R, n_vars: u32) -> IoResult<Vec<G2Affine>> {
-    (0..n_vars).map(|_| deserialize_g2(reader)).collect()
+fn deserialize_g2_vec(buf: &[u8]) -> Result<Vec<G2Affine>, ZkeyError> {
+    buf.par_chunks_exact(G2_SIZE as usize)
+        .map(|mut buf| deserialize_g2(&mut buf))
+        .collect()
 }
 
 #[cfg(test)]
@@ -478,7 +847,7 @@ mod tests {
             .collect::<Vec<_>>();
         let expected = vec![g1_one(); n_vars];
 
-        let de = deserialize_g1_vec(&mut &buf[..], n_vars as u32).unwrap();
+        let de = deserialize_g1_vec(&buf).unwrap();
         assert_eq!(expected, de);
     }
 
@@ -492,6 +861,27 @@ mod tests {
         assert_eq!(g2, expected);
     }
 
+    #[test]
+    fn can_deser_infinity() {
+        use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+        // snarkjs' (0, 0), decoded as the canonical point at infinity, which passes
+        // validation and is unchanged by arkworks' serialization
+        let g1 = deserialize_g1(&mut &[0u8; 64][..]).unwrap();
+        assert_eq!(g1, G1Affine::zero());
+        assert_eq!(Validation::Full.check(&[g1]), Ok(()));
+        let g2 = deserialize_g2(&mut &[0u8; 128][..]).unwrap();
+        assert_eq!(g2, G2Affine::zero());
+        assert_eq!(Validation::Full.check(&[g2]), Ok(()));
+
+        let mut bytes = vec![];
+        (g1, g2).serialize(&mut bytes).unwrap();
+        assert_eq!(
+            <(G1Affine, G2Affine)>::deserialize(&bytes[..]).unwrap(),
+            (g1, g2)
+        );
+    }
+
     #[test]
     fn can_deser_g2_vec() {
         let n_vars = 10;
@@ -502,7 +892,7 @@ mod tests {
             .collect::<Vec<_>>();
         let expected = vec![g2_one(); n_vars];
 
-        let de = deserialize_g2_vec(&mut &buf[..], n_vars as u32).unwrap();
+        let de = deserialize_g2_vec(&buf).unwrap();
         assert_eq!(expected, de);
     }
 
@@ -532,6 +922,142 @@ mod tests {
         assert_eq!(header.power, 2);
     }
 
+    #[test]
+    fn header_and_vk() {
+        let path = "./test-vectors/test.zkey";
+        let expected = ZkeyHeader {
+            n8q: 32,
+            n8r: 32,
+            n_vars: 4,
+            n_public: 1,
+            domain_size: 4,
+            circuit_hash: hex_literal::hex!("4289918fb00ce352b426119d49059905382c440e3439767db58836b96ce102b4ea11be6746375b98850f3fe6d5db5730122e33c765c9a1ec5e9480aacbb49b5d"),
+            num_contributions: 0,
+        };
+        let header = read_zkey_header(&mut File::open(path).unwrap()).unwrap();
+        assert_eq!(header, expected);
+
+        let (pk, _) = read_zkey(&mut File::open(path).unwrap()).unwrap();
+        let (vk, header) = read_zkey_vk(&mut File::open(path).unwrap()).unwrap();
+        assert_eq!(vk, pk.vk);
+        assert_eq!(header, expected);
+
+        // the proving key's sections are not read
+        let mut data = std::fs::read(path).unwrap();
+        data[1452] ^= 1;
+        read_zkey_vk(&mut std::io::Cursor::new(data)).unwrap();
+    }
+
+    fn read_zkey_err(data: &[u8]) -> ZkeyError {
+        read_zkey(&mut std::io::Cursor::new(data)).err().unwrap()
+    }
+
+    #[test]
+    fn malformed() {
+        let data = std::fs::read("./test-vectors/test.zkey").unwrap();
+
+        let mut bad = data.clone();
+        bad[0] = b'x';
+        assert!(matches!(read_zkey_err(&bad), ZkeyError::InvalidMagic(_)));
+
+        assert!(matches!(
+            read_zkey_err(&data[..2000]),
+            ZkeyError::SectionOutOfBounds { section: 7, .. }
+        ));
+        let err: std::io::Error = read_zkey_err(&data[..10]).into();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        // prover type of section 1
+        let mut bad = data.clone();
+        bad[24] = 2;
+        assert!(matches!(
+            read_zkey_err(&bad),
+            ZkeyError::UnsupportedProver(2)
+        ));
+
+        // id of the L query section
+        let mut bad = data.clone();
+        bad[1300] = 11;
+        assert!(matches!(read_zkey_err(&bad), ZkeyError::MissingSection(8)));
+
+        // the domain size can't be trusted for allocations
+        let mut bad = data.clone();
+        bad[120..124].copy_from_slice(&(1u32 << 31).to_le_bytes());
+        assert!(matches!(
+            read_zkey_err(&bad),
+            ZkeyError::SectionSize { section: 9, .. }
+        ));
+
+        // signal of the first coefficient
+        let mut bad = data;
+        bad[724..728].copy_from_slice(&100u32.to_le_bytes());
+        assert!(matches!(
+            read_zkey_err(&bad),
+            ZkeyError::InvalidCoefficient {
+                index: 0,
+                signal: 100,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn decode_matrices_in_chunks() {
+        let data = std::fs::read("./test-vectors/test.zkey").unwrap();
+        let mut reader = std::io::Cursor::new(&data[..]);
+        let mut binfile = BinFile::new(&mut reader).unwrap();
+        let header = binfile.groth_header().unwrap();
+        let size = binfile.get_section(4).unwrap().size;
+        let buf = binfile.read_section(4, size).unwrap();
+
+        let matrices = decode_matrices(&buf, &header).unwrap();
+        for coeffs_per_chunk in [1, 3] {
+            let chunked = decode_matrices_by(&buf, &header, coeffs_per_chunk).unwrap();
+            assert_eq!((&chunked.a, &chunked.b), (&matrices.a, &matrices.b));
+        }
+
+        // signal of the last of the 4 coefficients, numbered across chunks
+        let mut bad = buf;
+        let pos = 4 + 3 * COEFF_SIZE as usize + 8;
+        bad[pos..pos + 4].copy_from_slice(&100u32.to_le_bytes());
+        assert!(matches!(
+            decode_matrices_by(&bad, &header, 3),
+            Err(ZkeyError::InvalidCoefficient {
+                index: 3,
+                signal: 100,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn invalid_points() {
+        let data = std::fs::read("./test-vectors/test.zkey").unwrap();
+
+        // x coordinate of the first point of the B G2 query
+        let mut bad = data.clone();
+        bad[1988] ^= 1;
+        assert!(matches!(
+            read_zkey_err(&bad),
+            ZkeyError::InvalidPoint {
+                section: 7,
+                source: InvalidPoint::NotOnCurve { index: 0 }
+            }
+        ));
+        read_zkey_with_validation(&mut std::io::Cursor::new(&bad), Validation::Unchecked).unwrap();
+
+        // gamma_g2 of the verifying key
+        let mut bad = data;
+        bad[380] ^= 1;
+        assert!(matches!(
+            read_zkey_err(&bad),
+            ZkeyError::InvalidPoint {
+                section: 2,
+                source: InvalidPoint::NotOnCurve { index: 1 }
+            }
+        ));
+    }
+
     #[test]
     fn deser_key() {
         let path = "./test-vectors/test.zkey";
@@ -0,0 +1,302 @@
+//! Security audit of a Groth16 zkey
+//!
+//! A zkey can be well formed and still unsafe to use: the output of `snarkjs zkey new` has
+//! `delta = gamma = 1`, so anyone can forge proofs with it until a contribution replaces
+//! delta. The audit flags such keys, together with keys which would only produce invalid
+//! proofs.
+use ark_bn254::{Bn254, Fr, G1Affine, G2Affine};
+use ark_ec::{msm::VariableBaseMSM, AffineCurve, PairingEngine};
+use ark_ff::{PrimeField, Zero};
+use ark_groth16::ProvingKey;
+use ark_serialize::CanonicalSerialize;
+use blake2::{Blake2b, Digest};
+use rayon::prelude::*;
+
+use std::{
+    fmt,
+    io::{Read, Seek},
+};
+
+use super::{BinFile, ZkeyError, ZkeyHeader};
+
+/// How dangerous a finding is, from the least to the most severe
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The key is usable, but its setup can't be fully trusted
+    Warning,
+    /// The key produces proofs which don't verify
+    Error,
+    /// Proofs can be forged with the key
+    Critical,
+}
+
+/// A point of the verifying key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyPoint {
+    Alpha,
+    BetaG1,
+    BetaG2,
+    GammaG2,
+    DeltaG1,
+    DeltaG2,
+    /// Point of the IC, i.e. `gamma_abc_g1`, for the public signal at the index
+    Ic(usize),
+}
+
+/// An issue found by [`audit_zkey`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Issue {
+    /// delta equals gamma, which lets public inputs be moved into the C point of a proof
+    DeltaEqualsGamma,
+    /// delta is the generator, i.e. its discrete logarithm 1 is public
+    DeltaIsGenerator,
+    PointAtInfinity(KeyPoint),
+    /// The B query's G1 and G2 points at the indices don't have the same discrete logarithm
+    InconsistentBQuery {
+        indices: Vec<usize>,
+    },
+    HQueryLength {
+        len: usize,
+        domain_size: usize,
+    },
+    /// No contribution was made to the phase 2 ceremony
+    NoContributions,
+}
+
+impl Issue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Issue::DeltaEqualsGamma | Issue::DeltaIsGenerator | Issue::PointAtInfinity(_) => {
+                Severity::Critical
+            }
+            Issue::InconsistentBQuery { .. } | Issue::HQueryLength { .. } => Severity::Error,
+            Issue::NoContributions => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::DeltaEqualsGamma => write!(f, "delta equals gamma"),
+            Issue::DeltaIsGenerator => write!(f, "delta is the generator"),
+            Issue::PointAtInfinity(point) => write!(f, "{:?} is the point at infinity", point),
+            Issue::InconsistentBQuery { indices } => write!(
+                f,
+                "the G1 and G2 points of the B query differ at indices {:?}",
+                indices
+            ),
+            Issue::HQueryLength { len, domain_size } => write!(
+                f,
+                "the H query has {} points for a domain of size {}",
+                len, domain_size
+            ),
+            Issue::NoContributions => write!(f, "no contribution was made to the ceremony"),
+        }
+    }
+}
+
+/// The issues found in a zkey, from the most to the least severe
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuditReport {
+    pub issues: Vec<Issue>,
+}
+
+impl AuditReport {
+    /// Severity of the worst issue, if any
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.issues.first().map(Issue::severity)
+    }
+
+    /// Whether proofs can be forged with the key
+    pub fn is_critical(&self) -> bool {
+        self.max_severity() == Some(Severity::Critical)
+    }
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.issues.is_empty() {
+            return write!(f, "no issues found");
+        }
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{:?}: {}", issue.severity(), issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the zkey and audits it with [`audit_proving_key`]
+pub fn audit_zkey<R: Read + Seek>(reader: &mut R) -> Result<AuditReport, ZkeyError> {
+    let mut binfile = BinFile::new(reader)?;
+    let header = binfile.groth_header()?;
+    let pk = binfile.proving_key(&header)?;
+    let header = binfile.zkey_header(&header)?;
+    Ok(audit_proving_key(&pk, &header))
+}
+
+/// Audits a proving key read from the zkey with the header `header`
+///
+/// The B query is checked with a single pairing over random linear combinations of its G1
+/// and G2 points, and point by point only if that fails.
+pub fn audit_proving_key(pk: &ProvingKey<Bn254>, header: &ZkeyHeader) -> AuditReport {
+    let vk = &pk.vk;
+    let mut issues = vec![];
+
+    if vk.delta_g2 == vk.gamma_g2 {
+        issues.push(Issue::DeltaEqualsGamma);
+    }
+    if vk.delta_g2 == G2Affine::prime_subgroup_generator()
+        || pk.delta_g1 == G1Affine::prime_subgroup_generator()
+    {
+        issues.push(Issue::DeltaIsGenerator);
+    }
+
+    let points = [
+        (KeyPoint::Alpha, vk.alpha_g1.is_zero()),
+        (KeyPoint::BetaG1, pk.beta_g1.is_zero()),
+        (KeyPoint::BetaG2, vk.beta_g2.is_zero()),
+        (KeyPoint::GammaG2, vk.gamma_g2.is_zero()),
+        (KeyPoint::DeltaG1, pk.delta_g1.is_zero()),
+        (KeyPoint::DeltaG2, vk.delta_g2.is_zero()),
+    ];
+    let ic = vk
+        .gamma_abc_g1
+        .iter()
+        .enumerate()
+        .map(|(i, point)| (KeyPoint::Ic(i), point.is_zero()));
+    issues.extend(
+        points
+            .iter()
+            .copied()
+            .chain(ic)
+            .filter(|(_, infinity)| *infinity)
+            .map(|(point, _)| Issue::PointAtInfinity(point)),
+    );
+
+    let indices = inconsistent_b_query(&pk.b_g1_query, &pk.b_g2_query);
+    if !indices.is_empty() {
+        issues.push(Issue::InconsistentBQuery { indices });
+    }
+
+    if pk.h_query.len() != header.domain_size {
+        issues.push(Issue::HQueryLength {
+            len: pk.h_query.len(),
+            domain_size: header.domain_size,
+        });
+    }
+
+    if header.num_contributions == 0 {
+        issues.push(Issue::NoContributions);
+    }
+
+    // stable, so issues of the same severity keep the order of the checks
+    issues.sort_by_key(|issue| std::cmp::Reverse(issue.severity()));
+    AuditReport { issues }
+}
+
+// indices at which e(b_g1[i], g2) != e(g1, b_g2[i]), including the extra points of the
+// longer query
+fn inconsistent_b_query(b_g1: &[G1Affine], b_g2: &[G2Affine]) -> Vec<usize> {
+    let len = b_g1.len().min(b_g2.len());
+    let (b_g1, b_g2, extra) = (&b_g1[..len], &b_g2[..len], len..b_g1.len().max(b_g2.len()));
+
+    let scalars = challenges(b_g1, b_g2);
+    let g1 = VariableBaseMSM::multi_scalar_mul(b_g1, &scalars);
+    let g2 = VariableBaseMSM::multi_scalar_mul(b_g2, &scalars);
+    if Bn254::pairing(g1, G2Affine::prime_subgroup_generator())
+        == Bn254::pairing(G1Affine::prime_subgroup_generator(), g2)
+    {
+        return extra.collect();
+    }
+
+    (0..len)
+        .into_par_iter()
+        .filter(|&i| {
+            Bn254::pairing(b_g1[i], G2Affine::prime_subgroup_generator())
+                != Bn254::pairing(G1Affine::prime_subgroup_generator(), b_g2[i])
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .chain(extra)
+        .collect()
+}
+
+// 128-bit scalars derived from the hash of the points, so that they can't be chosen by
+// whoever crafted the key
+fn challenges(b_g1: &[G1Affine], b_g2: &[G2Affine]) -> Vec<<Fr as PrimeField>::BigInt> {
+    let mut hasher = Blake2b::new();
+    b_g1.serialize_uncompressed(&mut hasher)
+        .expect("hashing can't fail");
+    b_g2.serialize_uncompressed(&mut hasher)
+        .expect("hashing can't fail");
+    let seed = hasher.finalize();
+
+    (0..b_g1.len() as u64)
+        .into_par_iter()
+        .map(|i| {
+            let hash = Blake2b::new().chain(seed).chain(i.to_le_bytes()).finalize();
+            Fr::from_le_bytes_mod_order(&hash[..16]).into_repr()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_zkey, read_zkey_header};
+    use std::fs::File;
+
+    const PATH: &str = "./test-vectors/test.zkey";
+
+    #[test]
+    fn fresh_zkey() {
+        let report = audit_zkey(&mut File::open(PATH).unwrap()).unwrap();
+        // the test zkey comes straight from `zkey new`
+        assert_eq!(
+            report.issues,
+            vec![
+                Issue::DeltaEqualsGamma,
+                Issue::DeltaIsGenerator,
+                Issue::NoContributions
+            ]
+        );
+        assert!(report.is_critical());
+    }
+
+    #[test]
+    fn tampered_key() {
+        let (mut pk, _) = read_zkey(&mut File::open(PATH).unwrap()).unwrap();
+        let mut header = read_zkey_header(&mut File::open(PATH).unwrap()).unwrap();
+        header.num_contributions = 1;
+
+        let delta = pk.vk.delta_g2.mul(3u64).into();
+        pk.vk.delta_g2 = delta;
+        pk.delta_g1 = pk.delta_g1.mul(3u64).into();
+        assert_eq!(audit_proving_key(&pk, &header), AuditReport::default());
+
+        pk.vk.gamma_abc_g1[1] = G1Affine::zero();
+        let i = pk.b_g2_query.iter().position(|p| !p.is_zero()).unwrap();
+        pk.b_g2_query[i] = pk.b_g2_query[i].mul(2u64).into();
+        pk.b_g1_query.push(G1Affine::prime_subgroup_generator());
+        pk.h_query.pop();
+        let report = audit_proving_key(&pk, &header);
+        assert_eq!(
+            report.issues,
+            vec![
+                Issue::PointAtInfinity(KeyPoint::Ic(1)),
+                Issue::InconsistentBQuery {
+                    indices: vec![i, 4]
+                },
+                Issue::HQueryLength {
+                    len: 3,
+                    domain_size: 4
+                },
+            ]
+        );
+        assert_eq!(report.max_severity(), Some(Severity::Critical));
+    }
+}
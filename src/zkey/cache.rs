@@ -0,0 +1,413 @@
+//! Cache of a decoded zkey in arkworks' serialization format
+//!
+//! Decoding a zkey converts and validates every point. The cache stores the resulting
+//! proving key and matrices as they are in memory, so that reloading them is mostly a copy.
+//! It starts with the blake2b hash of the zkey it was generated from, which makes
+//! [`read_zkey_cached`] regenerate it whenever the zkey changes, and with the zkey's size and
+//! modification time, which spare hashing an unchanged zkey.
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::ProvingKey;
+use ark_relations::r1cs::ConstraintMatrices;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use blake2::{Blake2b, Digest};
+use memmap2::Mmap;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::{
+    fs::{self, File, Metadata, OpenOptions},
+    io::{BufReader, BufWriter, Cursor, Read, Write},
+    path::Path,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::UNIX_EPOCH,
+};
+
+use super::{read_zkey, ZkeyError};
+
+const MAGIC: &[u8; 8] = b"zkcache1";
+
+type Cached = (ProvingKey<Bn254>, ConstraintMatrices<Fr>);
+
+/// Blake2b hash of a zkey's content
+pub type ZkeyHash = [u8; 64];
+
+/// Whether the points of a cache are stored compressed
+///
+/// Compression halves the size of the cache, but reloading has to decompress and validate
+/// every point, while uncompressed caches are reloaded without any check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Uncompressed,
+    Compressed,
+}
+
+/// Hashes the zkey read from `reader`
+pub fn hash_zkey<R: Read>(mut reader: R) -> std::io::Result<ZkeyHash> {
+    let mut hasher = Blake2b::new();
+    let mut buf = vec![0; 1 << 16];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    let mut hash = [0; 64];
+    hash.copy_from_slice(&hasher.finalize());
+    Ok(hash)
+}
+
+/// Writes the cache of the proving key and matrices read from the zkey hashing to
+/// `zkey_hash`
+pub fn write_zkey_cache<W: Write>(
+    writer: W,
+    pk: &ProvingKey<Bn254>,
+    matrices: &ConstraintMatrices<Fr>,
+    zkey_hash: &ZkeyHash,
+    compression: Compression,
+) -> Result<(), ZkeyError> {
+    write_cache(writer, pk, matrices, zkey_hash, Stamp::UNKNOWN, compression)
+}
+
+// Size and modification time of a zkey file. `read_zkey_cached` trusts a zkey with the
+// stamp recorded in the cache to be unchanged, and only hashes it otherwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Stamp {
+    len: u64,
+    secs: u64,
+    nanos: u32,
+}
+
+impl Stamp {
+    // for caches written without a zkey file, or if the platform has no modification times
+    const UNKNOWN: Stamp = Stamp {
+        len: 0,
+        secs: 0,
+        nanos: 0,
+    };
+
+    fn of(metadata: &Metadata) -> Stamp {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+        match modified {
+            Some(modified) => Stamp {
+                len: metadata.len(),
+                secs: modified.as_secs(),
+                nanos: modified.subsec_nanos(),
+            },
+            None => Stamp::UNKNOWN,
+        }
+    }
+
+    fn is_known(&self) -> bool {
+        *self != Stamp::UNKNOWN
+    }
+}
+
+fn write_cache<W: Write>(
+    mut writer: W,
+    pk: &ProvingKey<Bn254>,
+    matrices: &ConstraintMatrices<Fr>,
+    zkey_hash: &ZkeyHash,
+    stamp: Stamp,
+    compression: Compression,
+) -> Result<(), ZkeyError> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[(compression == Compression::Compressed) as u8])?;
+    writer.write_all(zkey_hash)?;
+    writer.write_u64::<LittleEndian>(stamp.len)?;
+    writer.write_u64::<LittleEndian>(stamp.secs)?;
+    writer.write_u32::<LittleEndian>(stamp.nanos)?;
+
+    serialize(pk, &mut writer, compression)?;
+    for size in [
+        matrices.num_instance_variables,
+        matrices.num_witness_variables,
+        matrices.num_constraints,
+        matrices.a_num_non_zero,
+        matrices.b_num_non_zero,
+        matrices.c_num_non_zero,
+    ] {
+        serialize(&size, &mut writer, compression)?;
+    }
+    for matrix in [&matrices.a, &matrices.b, &matrices.c] {
+        serialize(matrix, &mut writer, compression)?;
+    }
+    Ok(())
+}
+
+fn serialize<T: CanonicalSerialize, W: Write>(
+    value: &T,
+    writer: W,
+    compression: Compression,
+) -> Result<(), SerializationError> {
+    match compression {
+        Compression::Compressed => value.serialize(writer),
+        Compression::Uncompressed => value.serialize_uncompressed(writer),
+    }
+}
+
+// compressed points are validated when decompressed, uncompressed ones are trusted
+fn deserialize<T: CanonicalDeserialize, R: Read>(
+    reader: R,
+    compression: Compression,
+) -> Result<T, SerializationError> {
+    match compression {
+        Compression::Compressed => T::deserialize(reader),
+        Compression::Uncompressed => T::deserialize_unchecked(reader),
+    }
+}
+
+/// Reads a cache written by [`write_zkey_cache`], returning `None` if it was generated from
+/// another zkey than the one hashing to `zkey_hash`
+pub fn read_zkey_cache<R: Read>(
+    mut reader: R,
+    zkey_hash: &ZkeyHash,
+) -> Result<Option<Cached>, ZkeyError> {
+    let (compression, hash, _) = read_header(&mut reader)?;
+    if &hash != zkey_hash {
+        return Ok(None);
+    }
+    read_body(reader, compression).map(Some)
+}
+
+fn read_header<R: Read>(mut reader: R) -> Result<(Compression, ZkeyHash, Stamp), ZkeyError> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(ZkeyError::InvalidCache);
+    }
+
+    let compression = match reader.read_u8()? {
+        0 => Compression::Uncompressed,
+        1 => Compression::Compressed,
+        _ => return Err(ZkeyError::InvalidCache),
+    };
+    let mut hash = [0; 64];
+    reader.read_exact(&mut hash)?;
+    let stamp = Stamp {
+        len: reader.read_u64::<LittleEndian>()?,
+        secs: reader.read_u64::<LittleEndian>()?,
+        nanos: reader.read_u32::<LittleEndian>()?,
+    };
+    Ok((compression, hash, stamp))
+}
+
+fn read_body<R: Read>(mut reader: R, compression: Compression) -> Result<Cached, ZkeyError> {
+    let pk = deserialize(&mut reader, compression)?;
+    let matrices = ConstraintMatrices {
+        num_instance_variables: deserialize(&mut reader, compression)?,
+        num_witness_variables: deserialize(&mut reader, compression)?,
+        num_constraints: deserialize(&mut reader, compression)?,
+        a_num_non_zero: deserialize(&mut reader, compression)?,
+        b_num_non_zero: deserialize(&mut reader, compression)?,
+        c_num_non_zero: deserialize(&mut reader, compression)?,
+        a: deserialize(&mut reader, compression)?,
+        b: deserialize(&mut reader, compression)?,
+        c: deserialize(&mut reader, compression)?,
+    };
+    Ok((pk, matrices))
+}
+
+/// Reads the zkey at `zkey_path` through the cache at `cache_path`
+///
+/// The cache is used if it was generated from the current content of the zkey. Otherwise,
+/// e.g. if it is missing, stale or corrupted, the zkey is read with [`read_zkey`] and the
+/// cache is replaced atomically.
+///
+/// The cache records the size and modification time of the zkey. As long as they are
+/// unchanged, the zkey is trusted to be too and is not read at all. Otherwise it is hashed,
+/// and a cache whose hash still matches, e.g. after the zkey was copied, is rewritten with
+/// the new size and modification time.
+pub fn read_zkey_cached(
+    zkey_path: impl AsRef<Path>,
+    cache_path: impl AsRef<Path>,
+    compression: Compression,
+) -> Result<Cached, ZkeyError> {
+    let zkey = File::open(zkey_path)?;
+    let stamp = Stamp::of(&zkey.metadata()?);
+
+    let cache_path = cache_path.as_ref();
+    let cache = File::open(cache_path).ok().and_then(|cache| {
+        let mut reader = BufReader::new(cache);
+        let header = read_header(&mut reader).ok()?;
+        Some((header, reader))
+    });
+
+    // Safety: the map is read-only, the zkey must not be modified while it is read
+    let zkey = unsafe { Mmap::map(&zkey)? };
+    let mut zkey_hash = None;
+    if let Some(((cached_compression, cached_hash, cached_stamp), mut reader)) = cache {
+        let unchanged = stamp.is_known() && stamp == cached_stamp;
+        if !unchanged {
+            zkey_hash = Some(hash_zkey(&zkey[..])?);
+        }
+        if unchanged || zkey_hash == Some(cached_hash) {
+            if let Ok((pk, matrices)) = read_body(&mut reader, cached_compression) {
+                // records the new stamp, to not hash the zkey again
+                if !unchanged {
+                    write_cache_file(cache_path, &pk, &matrices, &cached_hash, stamp, compression)?;
+                }
+                return Ok((pk, matrices));
+            }
+        }
+    }
+
+    let zkey_hash = match zkey_hash {
+        Some(hash) => hash,
+        None => hash_zkey(&zkey[..])?,
+    };
+    let (pk, matrices) = read_zkey(&mut Cursor::new(&zkey[..]))?;
+    write_cache_file(cache_path, &pk, &matrices, &zkey_hash, stamp, compression)?;
+    Ok((pk, matrices))
+}
+
+// Writes the cache to a temporary file then renames it over `path`. The temporary file is
+// unique to the process and the call, so that concurrent writers don't clobber each other.
+fn write_cache_file(
+    path: &Path,
+    pk: &ProvingKey<Bn254>,
+    matrices: &ConstraintMatrices<Fr>,
+    zkey_hash: &ZkeyHash,
+    stamp: Stamp,
+    compression: Compression,
+) -> Result<(), ZkeyError> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(format!(
+        ".{}.{}.tmp",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let write = || -> Result<(), ZkeyError> {
+        let tmp = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)?;
+        let mut writer = BufWriter::new(tmp);
+        write_cache(&mut writer, pk, matrices, zkey_hash, stamp, compression)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    };
+    let res = write();
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const PATH: &str = "./test-vectors/test.zkey";
+
+    fn assert_matrices_eq(a: &ConstraintMatrices<Fr>, b: &ConstraintMatrices<Fr>) {
+        assert_eq!(a.num_instance_variables, b.num_instance_variables);
+        assert_eq!(a.num_witness_variables, b.num_witness_variables);
+        assert_eq!(a.num_constraints, b.num_constraints);
+        assert_eq!(a.a_num_non_zero, b.a_num_non_zero);
+        assert_eq!(a.b_num_non_zero, b.b_num_non_zero);
+        assert_eq!((&a.a, &a.b, &a.c), (&b.a, &b.b, &b.c));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let (pk, matrices) = read_zkey(&mut File::open(PATH).unwrap()).unwrap();
+        let hash = hash_zkey(File::open(PATH).unwrap()).unwrap();
+
+        let mut sizes = vec![];
+        for compression in [Compression::Uncompressed, Compression::Compressed] {
+            let mut cache = vec![];
+            write_zkey_cache(&mut cache, &pk, &matrices, &hash, compression).unwrap();
+            sizes.push(cache.len());
+
+            let (pk2, matrices2) = read_zkey_cache(&cache[..], &hash).unwrap().unwrap();
+            assert_eq!(pk2, pk);
+            assert_matrices_eq(&matrices2, &matrices);
+
+            let mut other = hash;
+            other[0] ^= 1;
+            assert!(read_zkey_cache(&cache[..], &other).unwrap().is_none());
+        }
+        assert!(sizes[1] < sizes[0]);
+    }
+
+    // sets the modification time of the file at `path` to `secs` after the epoch
+    fn set_modified(path: &Path, secs: u64) {
+        let file = OpenOptions::new().write(true).open(path).unwrap();
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(secs))
+            .unwrap();
+    }
+
+    fn cached_header(cache_path: &Path) -> (Compression, ZkeyHash, Stamp) {
+        read_header(File::open(cache_path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn invalidation() {
+        let dir = std::env::temp_dir().join(format!("ark-circom-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let zkey_path = dir.join("test.zkey");
+        let cache_path = dir.join("test.zkey.cache");
+        let mut data = fs::read(PATH).unwrap();
+        fs::write(&zkey_path, &data).unwrap();
+        set_modified(&zkey_path, 1000);
+
+        let (pk, _) = read_zkey_cached(&zkey_path, &cache_path, Compression::Compressed).unwrap();
+        let hash = hash_zkey(&data[..]).unwrap();
+        let cache = fs::read(&cache_path).unwrap();
+        assert!(read_zkey_cache(&cache[..], &hash).unwrap().is_some());
+        let (cached, _) =
+            read_zkey_cached(&zkey_path, &cache_path, Compression::Compressed).unwrap();
+        assert_eq!(cached, pk);
+
+        // the zkey is not hashed while its size and modification time are unchanged, which
+        // is seen by the cache being used despite a wrong hash
+        let mut cache = fs::read(&cache_path).unwrap();
+        let hash_range = MAGIC.len() + 1..MAGIC.len() + 65;
+        cache[hash_range.clone()].fill(0);
+        fs::write(&cache_path, &cache).unwrap();
+        let (cached, _) =
+            read_zkey_cached(&zkey_path, &cache_path, Compression::Compressed).unwrap();
+        assert_eq!(cached, pk);
+        assert_eq!(fs::read(&cache_path).unwrap(), cache);
+
+        // a new modification time with the same content only refreshes the stamp
+        cache[hash_range].copy_from_slice(&hash);
+        fs::write(&cache_path, &cache).unwrap();
+        set_modified(&zkey_path, 2000);
+        let (cached, _) =
+            read_zkey_cached(&zkey_path, &cache_path, Compression::Compressed).unwrap();
+        assert_eq!(cached, pk);
+        let (_, cached_hash, stamp) = cached_header(&cache_path);
+        assert_eq!((cached_hash, stamp.secs), (hash, 2000));
+
+        // changing the circuit hash stored in the contributions section
+        data[2512] ^= 1;
+        fs::write(&zkey_path, &data).unwrap();
+        set_modified(&zkey_path, 3000);
+        read_zkey_cached(&zkey_path, &cache_path, Compression::Uncompressed).unwrap();
+        let cache = fs::read(&cache_path).unwrap();
+        assert!(read_zkey_cache(&cache[..], &hash).unwrap().is_none());
+        let hash = hash_zkey(&data[..]).unwrap();
+        assert!(read_zkey_cache(&cache[..], &hash).unwrap().is_some());
+
+        // a corrupted cache is regenerated
+        fs::write(&cache_path, b"garbage").unwrap();
+        let (regenerated, _) =
+            read_zkey_cached(&zkey_path, &cache_path, Compression::Uncompressed).unwrap();
+        assert_eq!(regenerated, pk);
+
+        // without leaving temporary files behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
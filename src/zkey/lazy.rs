@@ -0,0 +1,307 @@
+//! Lazy access to the sections of a zkey
+use ark_bn254::{Bn254, Fr, G1Affine, G2Affine};
+use ark_groth16::{ProvingKey, VerifyingKey};
+use ark_relations::r1cs::ConstraintMatrices;
+use memmap2::Mmap;
+
+use std::{collections::HashMap, fmt, fs::File, io::Cursor, ops::Range, path::Path};
+
+use super::{
+    check_points, decode_coefficients, decode_matrices, deserialize_g1_vec, deserialize_g2_vec,
+    num_coefficients, BinFile, Coefficient, HeaderGroth, Section, ZkeyError, ZkeyHeader,
+    COEFF_SIZE, G1_SIZE, G2_SIZE,
+};
+use crate::Validation;
+
+/// A zkey whose sections are decoded on demand, usually backed by a memory-mapped file
+///
+/// Opening a zkey only parses the section table and the header, so reading the verifying
+/// key of a large circuit doesn't load its proving key. Every call to a query decodes its
+/// section again, in parallel, so the returned vectors should be kept if they are reused.
+/// The `_range` methods decode only part of a query, to go through it in chunks.
+///
+/// ```rust,ignore
+/// let zkey = Zkey::open("circuit.zkey")?;
+/// let vk = zkey.vk()?;
+/// let h_query = zkey.h_query()?;
+/// ```
+pub struct Zkey<B = Mmap> {
+    data: B,
+    sections: HashMap<u32, Vec<Section>>,
+    header: HeaderGroth,
+    zkey_header: ZkeyHeader,
+    validation: Validation,
+}
+
+// leaves out the content of the zkey, which can be gigabytes
+impl<B> fmt::Debug for Zkey<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Zkey")
+            .field("header", &self.zkey_header)
+            .field("validation", &self.validation)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Zkey<Mmap> {
+    /// Memory-maps the zkey at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ZkeyError> {
+        let file = File::open(path)?;
+        // Safety: the map is read-only. As with any mmap, the file must not be truncated or
+        // modified while it is mapped.
+        let mmap = unsafe { Mmap::map(&file)? };
+        Self::from_bytes(mmap)
+    }
+}
+
+impl<B: AsRef<[u8]>> Zkey<B> {
+    /// Parses the section table and the header of the zkey held by `data`. The header's
+    /// points are always fully validated.
+    pub fn from_bytes(data: B) -> Result<Self, ZkeyError> {
+        let (sections, header, zkey_header) = {
+            let mut cursor = Cursor::new(data.as_ref());
+            let mut binfile = BinFile::new(&mut cursor)?;
+            let header = binfile.groth_header()?;
+            let zkey_header = binfile.zkey_header(&header)?;
+            (binfile.sections, header, zkey_header)
+        };
+
+        Ok(Self {
+            data,
+            sections,
+            header,
+            zkey_header,
+            validation: Validation::OnCurve,
+        })
+    }
+
+    /// Sets the validation of the points of the queries, see [`super::read_zkey_with_validation`]
+    pub fn with_validation(mut self, validation: Validation) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    /// The header, as read by [`super::read_zkey_header`]
+    pub fn header(&self) -> &ZkeyHeader {
+        &self.zkey_header
+    }
+
+    pub fn n_vars(&self) -> usize {
+        self.header.n_vars
+    }
+
+    pub fn n_public(&self) -> usize {
+        self.header.n_public
+    }
+
+    pub fn domain_size(&self) -> usize {
+        self.header.domain_size as usize
+    }
+
+    /// Decodes the verifying key, reading only the IC section besides the header
+    pub fn vk(&self) -> Result<VerifyingKey<Bn254>, ZkeyError> {
+        let vk_validation = match self.validation {
+            Validation::Unchecked => Validation::Unchecked,
+            _ => Validation::Full,
+        };
+        let header = &self.header.verifying_key;
+        Ok(VerifyingKey {
+            alpha_g1: header.alpha_g1,
+            beta_g2: header.beta_g2,
+            gamma_g2: header.gamma_g2,
+            delta_g2: header.delta_g2,
+            gamma_abc_g1: self.g1_section(
+                3,
+                self.n_public() + 1,
+                0..self.n_public() + 1,
+                vk_validation,
+            )?,
+        })
+    }
+
+    pub fn a_query(&self) -> Result<Vec<G1Affine>, ZkeyError> {
+        self.a_query_range(0..self.n_vars())
+    }
+
+    pub fn b_g1_query(&self) -> Result<Vec<G1Affine>, ZkeyError> {
+        self.b_g1_query_range(0..self.n_vars())
+    }
+
+    pub fn b_g2_query(&self) -> Result<Vec<G2Affine>, ZkeyError> {
+        self.b_g2_query_range(0..self.n_vars())
+    }
+
+    pub fn l_query(&self) -> Result<Vec<G1Affine>, ZkeyError> {
+        self.l_query_range(0..self.l_query_len())
+    }
+
+    pub fn h_query(&self) -> Result<Vec<G1Affine>, ZkeyError> {
+        self.h_query_range(0..self.domain_size())
+    }
+
+    /// Decodes the points of the A query at the indices `range`. Panics if the range is
+    /// out of the query.
+    pub fn a_query_range(&self, range: Range<usize>) -> Result<Vec<G1Affine>, ZkeyError> {
+        self.g1_section(5, self.n_vars(), range, self.validation)
+    }
+
+    pub fn b_g1_query_range(&self, range: Range<usize>) -> Result<Vec<G1Affine>, ZkeyError> {
+        self.g1_section(6, self.n_vars(), range, self.validation)
+    }
+
+    pub fn b_g2_query_range(&self, range: Range<usize>) -> Result<Vec<G2Affine>, ZkeyError> {
+        let buf = self.section(7, self.n_vars() as u64 * G2_SIZE)?;
+        let size = G2_SIZE as usize;
+        let points = deserialize_g2_vec(&buf[range.start * size..range.end * size])?;
+        check_points(&points, 7, range.start, self.validation)?;
+        Ok(points)
+    }
+
+    pub fn l_query_range(&self, range: Range<usize>) -> Result<Vec<G1Affine>, ZkeyError> {
+        self.g1_section(8, self.l_query_len(), range, self.validation)
+    }
+
+    pub fn h_query_range(&self, range: Range<usize>) -> Result<Vec<G1Affine>, ZkeyError> {
+        self.g1_section(9, self.domain_size(), range, self.validation)
+    }
+
+    /// The number of points of the L query, one per private variable
+    pub fn l_query_len(&self) -> usize {
+        self.n_vars() - self.n_public() - 1
+    }
+
+    pub fn matrices(&self) -> Result<ConstraintMatrices<Fr>, ZkeyError> {
+        let size = self.get_section(4)?.size;
+        decode_matrices(self.section(4, size)?, &self.header)
+    }
+
+    /// Decodes the whole proving key, as [`super::read_zkey`] does
+    pub fn proving_key(&self) -> Result<ProvingKey<Bn254>, ZkeyError> {
+        Ok(ProvingKey {
+            a_query: self.a_query()?,
+            b_g1_query: self.b_g1_query()?,
+            b_g2_query: self.b_g2_query()?,
+            h_query: self.h_query()?,
+            l_query: self.l_query()?,
+            ..self.proving_key_header()?
+        })
+    }
+
+    /// The proving key with empty queries, i.e. the verifying key and the points of the
+    /// header, for the provers which go through the queries in chunks
+    pub(crate) fn proving_key_header(&self) -> Result<ProvingKey<Bn254>, ZkeyError> {
+        Ok(ProvingKey {
+            vk: self.vk()?,
+            beta_g1: self.header.verifying_key.beta_g1,
+            delta_g1: self.header.verifying_key.delta_g1,
+            a_query: vec![],
+            b_g1_query: vec![],
+            b_g2_query: vec![],
+            h_query: vec![],
+            l_query: vec![],
+        })
+    }
+
+    /// The number of coefficients of the A and B matrices, including the constraints of
+    /// the public inputs which aren't part of [`Zkey::matrices`]
+    pub(crate) fn num_coefficients(&self) -> Result<usize, ZkeyError> {
+        let size = self.get_section(4)?.size;
+        num_coefficients(self.section(4, size)?)
+    }
+
+    /// Decodes the coefficients at the indices `range`, see [`Zkey::num_coefficients`]
+    pub(crate) fn coefficients(&self, range: Range<usize>) -> Result<Vec<Coefficient>, ZkeyError> {
+        let size = self.get_section(4)?.size;
+        let buf = &self.section(4, size)?[4..];
+        let coeff_size = COEFF_SIZE as usize;
+        decode_coefficients(
+            &buf[range.start * coeff_size..range.end * coeff_size],
+            range.start,
+            &self.header,
+        )
+    }
+
+    fn g1_section(
+        &self,
+        id: u32,
+        num: usize,
+        range: Range<usize>,
+        validation: Validation,
+    ) -> Result<Vec<G1Affine>, ZkeyError> {
+        let buf = self.section(id, num as u64 * G1_SIZE)?;
+        let size = G1_SIZE as usize;
+        let points = deserialize_g1_vec(&buf[range.start * size..range.end * size])?;
+        check_points(&points, id as usize, range.start, validation)?;
+        Ok(points)
+    }
+
+    fn get_section(&self, id: u32) -> Result<&Section, ZkeyError> {
+        self.sections
+            .get(&id)
+            .and_then(|sections| sections.first())
+            .ok_or(ZkeyError::MissingSection(id))
+    }
+
+    /// Returns the bytes of the section, checking that it is `size` bytes long. Sections
+    /// were checked to lie within the data when opening.
+    fn section(&self, id: u32, size: u64) -> Result<&[u8], ZkeyError> {
+        let section = self.get_section(id)?;
+        if section.size != size {
+            return Err(ZkeyError::SectionSize {
+                section: id,
+                size: section.size,
+                expected: size,
+            });
+        }
+        let start = section.position as usize;
+        Ok(&self.data.as_ref()[start..start + size as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read_zkey;
+
+    const PATH: &str = "./test-vectors/test.zkey";
+
+    #[test]
+    fn matches_read_zkey() {
+        let (pk, matrices) = read_zkey(&mut File::open(PATH).unwrap()).unwrap();
+        let zkey = Zkey::open(PATH).unwrap();
+
+        assert_eq!(zkey.n_vars(), 4);
+        assert_eq!(zkey.n_public(), 1);
+        assert_eq!(zkey.domain_size(), 4);
+        assert_eq!(zkey.header().num_contributions, 0);
+        assert_eq!(zkey.vk().unwrap(), pk.vk);
+        assert_eq!(zkey.h_query().unwrap(), pk.h_query);
+        assert_eq!(zkey.proving_key().unwrap(), pk);
+        assert_eq!(zkey.l_query_len(), pk.l_query.len());
+        assert_eq!(zkey.b_g2_query_range(1..3).unwrap(), pk.b_g2_query[1..3]);
+        assert_eq!(zkey.h_query_range(3..4).unwrap(), pk.h_query[3..]);
+
+        let lazy = zkey.matrices().unwrap();
+        assert_eq!(lazy.num_constraints, matrices.num_constraints);
+        assert_eq!(lazy.a, matrices.a);
+        assert_eq!(lazy.b, matrices.b);
+    }
+
+    #[test]
+    fn invalid_sections_are_reported_on_access() {
+        let mut data = std::fs::read(PATH).unwrap();
+        // x coordinate of the first point of the A query
+        data[1452] ^= 1;
+        let zkey = Zkey::from_bytes(data).unwrap();
+
+        zkey.vk().unwrap();
+        assert!(matches!(
+            zkey.a_query().unwrap_err(),
+            ZkeyError::InvalidPoint { section: 5, .. }
+        ));
+        assert!(zkey
+            .with_validation(Validation::Unchecked)
+            .a_query()
+            .is_ok());
+    }
+}
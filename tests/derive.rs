@@ -0,0 +1,67 @@
+use ark_circom::{CircomBuilder, CircomConfig, CircomInputs};
+use color_eyre::Result;
+use num_bigint::BigInt;
+
+use ark_bn254::{Bn254, Fr};
+use ethers_core::types::U256;
+
+#[derive(CircomInputs)]
+struct Multiplier {
+    a: Fr,
+    b: u64,
+}
+
+#[derive(CircomInputs)]
+struct Merkle {
+    leaf: U256,
+    #[circom(rename = "pathElements")]
+    path_elements: [[BigInt; 2]; 2],
+    #[circom(rename = "pathIndices")]
+    path_indices: Vec<bool>,
+    r#type: i32,
+}
+
+#[test]
+fn derived_inputs() {
+    let inputs = Merkle {
+        leaf: U256::from(1),
+        path_elements: [
+            [BigInt::from(2), BigInt::from(3)],
+            [BigInt::from(4), BigInt::from(5)],
+        ],
+        path_indices: vec![true, false],
+        r#type: -1,
+    }
+    .to_inputs();
+
+    assert_eq!(inputs.len(), 4);
+    assert_eq!(inputs["leaf"], vec![BigInt::from(1)]);
+    assert_eq!(
+        inputs["pathElements"],
+        (2..=5).map(BigInt::from).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        inputs["pathIndices"],
+        vec![BigInt::from(1), BigInt::from(0)]
+    );
+    assert_eq!(inputs["type"], vec![BigInt::from(-1)]);
+}
+
+#[test]
+fn derived_inputs_witness() -> Result<()> {
+    let cfg = CircomConfig::<Bn254>::new(
+        "./test-vectors/mycircuit.wasm",
+        "./test-vectors/mycircuit.r1cs",
+    )?;
+    let mut builder = CircomBuilder::new(cfg);
+    builder.push_inputs(&Multiplier {
+        a: Fr::from(3u64),
+        b: 11,
+    });
+
+    let circom = builder.build()?;
+    let inputs = circom.get_public_inputs().unwrap();
+    assert_eq!(inputs, vec![Fr::from(33u64)]);
+
+    Ok(())
+}
@@ -1,11 +1,19 @@
-use ark_circom::{CircomBuilder, CircomConfig};
+use ark_circom::{
+    circom::{check_constraints, CheckError},
+    ethereum, BatchProver, CircomBuilder, CircomConfig, CircomInputs, CircomProver, InputError,
+};
 use ark_std::rand::thread_rng;
 use color_eyre::Result;
+use num_bigint::BigInt;
 
-use ark_bn254::Bn254;
+use ark_bn254::{Bn254, Fr};
 use ark_groth16::{
-    create_random_proof as prove, generate_random_parameters, prepare_verifying_key, verify_proof,
+    create_proof_with_reduction_and_matrices, create_random_proof as prove,
+    generate_random_parameters, prepare_verifying_key, r1cs_to_qap::LibsnarkReduction,
+    verify_proof,
 };
+use ark_std::UniformRand;
+use std::collections::HashMap;
 
 #[test]
 fn groth16_proof() -> Result<()> {
@@ -38,6 +46,230 @@ fn groth16_proof() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn json_inputs() -> Result<()> {
+    let cfg = CircomConfig::<Bn254>::new(
+        "./test-vectors/mycircuit.wasm",
+        "./test-vectors/mycircuit.r1cs",
+    )?;
+    let mut builder = CircomBuilder::new(cfg.clone());
+    builder.push_input("a", 3);
+    builder.push_input("b", 11);
+
+    let mut json_builder = CircomBuilder::new(cfg);
+    json_builder.push_inputs_json(&std::fs::read_to_string(
+        "./test-vectors/mycircuit-input1.json",
+    )?)?;
+    assert_eq!(json_builder.inputs, builder.inputs);
+    assert_eq!(json_builder.build()?.witness, builder.build()?.witness);
+
+    Ok(())
+}
+
+#[test]
+fn named_witness() -> Result<()> {
+    let cfg = CircomConfig::<Bn254>::new(
+        "./test-vectors/mycircuit.wasm",
+        "./test-vectors/mycircuit.r1cs",
+    )?;
+    let mut builder = CircomBuilder::new(cfg);
+    builder.push_input("a", 3);
+    builder.push_input("b", 11);
+
+    let witness = builder.witness()?;
+    assert_eq!(witness.get("main.c"), Some(&33.into()));
+    assert_eq!(witness.get("a"), Some(&3.into()));
+    assert_eq!(witness.glob("main.?").len(), 3);
+    assert_eq!(witness.to_json()["main.b"], "11");
+
+    Ok(())
+}
+
+#[test]
+fn groth16_proof_public_signals() -> Result<()> {
+    let cfg = CircomConfig::<Bn254>::new(
+        "./test-vectors/mycircuit.wasm",
+        "./test-vectors/mycircuit.r1cs",
+    )?;
+    let mut builder = CircomBuilder::new(cfg);
+    builder.push_input("a", 3);
+    builder.push_input("b", 11);
+
+    let circom = builder.setup();
+
+    let mut rng = thread_rng();
+    let params = generate_random_parameters::<Bn254, _, _>(circom, &mut rng)?;
+
+    let circom = builder.build()?;
+    assert_eq!(
+        circom.public_signal_names(),
+        Some(&["main.c".to_string()][..])
+    );
+
+    let signals = circom.get_public_signals().unwrap();
+    assert_eq!(signals.outputs(), &[Fr::from(33u64)]);
+    assert!(signals.public_inputs().is_empty());
+    assert_eq!(signals.output("c"), Some(&Fr::from(33u64)));
+    assert_eq!(ethereum::Inputs::from(&signals).0, vec![33u64.into()]);
+
+    let proof = prove(circom, &params, &mut rng)?;
+
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let verified = verify_proof(&pvk, &proof, &signals)?;
+
+    assert!(verified);
+
+    Ok(())
+}
+
+#[test]
+fn unsatisfied_constraints_report() -> Result<()> {
+    let mut cfg = CircomConfig::<Bn254>::new(
+        "./test-vectors/mycircuit.wasm",
+        "./test-vectors/mycircuit.r1cs",
+    )?;
+    cfg.check_constraints = true;
+    let sym = cfg.sym.clone();
+    let mut builder = CircomBuilder::new(cfg);
+    builder.push_input("a", 3);
+    builder.push_input("b", 11);
+
+    let circom = builder.build()?;
+    let mut witness = circom.witness.clone().unwrap();
+    witness[1] = Fr::from(34u64);
+
+    let err = match check_constraints(&circom.r1cs, &witness, sym.as_ref()) {
+        Err(CheckError::Unsatisfied(err)) => err,
+        res => panic!("{:?}", res),
+    };
+    assert_eq!(err.constraints.len(), 1);
+    let report = err.to_string();
+    assert!(report.contains("main.c[34]"), "{}", report);
+    assert!(report.contains("-3 · 11 = -33 ≠ -34"), "{}", report);
+
+    Ok(())
+}
+
+#[test]
+fn groth16_proof_from_matrices() -> Result<()> {
+    let cfg = CircomConfig::<Bn254>::new(
+        "./test-vectors/mycircuit.wasm",
+        "./test-vectors/mycircuit.r1cs",
+    )?;
+    let mut builder = CircomBuilder::new(cfg);
+    builder.push_input("a", 3);
+    builder.push_input("b", 11);
+
+    let circom = builder.setup();
+
+    let mut rng = thread_rng();
+    let params = generate_random_parameters::<Bn254, _, _>(circom, &mut rng)?;
+
+    let circom = builder.build()?;
+    let inputs = circom.get_public_inputs().unwrap();
+
+    let matrices = circom.r1cs.to_matrices();
+    let full_assignment = circom
+        .r1cs
+        .permute_witness(circom.witness.as_ref().unwrap());
+    let proof = create_proof_with_reduction_and_matrices::<_, LibsnarkReduction>(
+        &params,
+        Fr::rand(&mut rng),
+        Fr::rand(&mut rng),
+        &matrices,
+        matrices.num_instance_variables,
+        matrices.num_constraints,
+        &full_assignment,
+    )?;
+
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let verified = verify_proof(&pvk, &proof, &inputs)?;
+
+    assert!(verified);
+
+    Ok(())
+}
+
+#[test]
+fn circom_prover() -> Result<()> {
+    let prover = CircomProver::new("./test-vectors/test.zkey", "./test-vectors/mycircuit.wasm")?;
+    assert!(!prover.can_validate_inputs());
+    let mut prover = prover.with_r1cs("./test-vectors/mycircuit.r1cs")?;
+    assert!(prover.can_validate_inputs());
+
+    let mut inputs = HashMap::new();
+    inputs.insert("a".to_string(), vec![3.into()]);
+    inputs.insert("b".to_string(), vec![11.into()]);
+    let (proof, signals) = prover.prove(&inputs)?;
+    assert_eq!(signals.output("c"), Some(&Fr::from(33u64)));
+    assert!(prover.verify(&proof, &signals)?);
+    assert!(!prover.verify(&proof, &[Fr::from(34u64)])?);
+
+    // the keys are reused for the next proofs
+    let (proof, signals) = prover.prove_json(r#"{"a": 5, "b": 7}"#)?;
+    assert_eq!(signals.outputs(), &[Fr::from(35u64)]);
+    assert!(prover.verify(&proof, &signals)?);
+
+    let err = prover.prove_json(r#"{"a": 3, "foo": 11}"#).unwrap_err();
+    let err = err.downcast_ref::<InputError>().unwrap();
+    assert_eq!(err.unknown, vec!["foo".to_string()]);
+
+    Ok(())
+}
+
+#[test]
+fn batch_prover() -> Result<()> {
+    let prover = CircomProver::new("./test-vectors/test.zkey", "./test-vectors/mycircuit.wasm")?
+        .with_r1cs("./test-vectors/mycircuit.r1cs")?;
+    let batch = BatchProver::with_jobs(prover, 3)?;
+    assert_eq!(batch.jobs(), 3);
+
+    let inputs = (1..=8u64)
+        .map(|a| {
+            let mut inputs = HashMap::new();
+            inputs.insert("a".to_string(), vec![a.into()]);
+            // one input is invalid, without failing the others
+            let name = if a == 5 { "foo" } else { "b" };
+            inputs.insert(name.to_string(), vec![3.into()]);
+            inputs
+        })
+        .collect::<Vec<_>>();
+    let results = batch.prove(&inputs);
+    assert_eq!(results.len(), 8);
+
+    for (a, result) in (1..=8u64).zip(results) {
+        if a == 5 {
+            let err = result.unwrap_err();
+            assert!(err.downcast_ref::<InputError>().is_some());
+            continue;
+        }
+        let (proof, signals) = result?;
+        assert_eq!(signals.outputs(), &[Fr::from(3 * a)]);
+        assert!(batch.verify(&proof, &signals)?);
+    }
+    assert!(batch.prove::<HashMap<String, Vec<_>>>(&[]).is_empty());
+
+    // a panic only fails the proof of its input
+    struct PanicsOnTwo(u64);
+    impl CircomInputs for PanicsOnTwo {
+        fn to_inputs(&self) -> HashMap<String, Vec<BigInt>> {
+            assert_ne!(self.0, 2, "no inputs for two");
+            let mut inputs = HashMap::new();
+            inputs.insert("a".to_string(), vec![self.0.into()]);
+            inputs.insert("b".to_string(), vec![3.into()]);
+            inputs
+        }
+    }
+    let results = batch.prove(&[PanicsOnTwo(1), PanicsOnTwo(2), PanicsOnTwo(3)]);
+    assert!(results[0].is_ok() && results[2].is_ok());
+    let err = results[1].as_ref().unwrap_err().to_string();
+    assert!(err.contains("no inputs for two"), "{}", err);
+
+    Ok(())
+}
+
 #[test]
 fn groth16_proof_wrong_input() {
     let cfg = CircomConfig::<Bn254>::new(
@@ -59,6 +291,41 @@ fn groth16_proof_wrong_input() {
     builder.build().unwrap_err();
 }
 
+#[test]
+fn invalid_inputs_report() -> Result<()> {
+    let cfg = CircomConfig::<Bn254>::new(
+        "./test-vectors/mycircuit.wasm",
+        "./test-vectors/mycircuit.r1cs",
+    )?;
+    let mut builder = CircomBuilder::new(cfg);
+    builder.push_input("a", 3);
+    builder.push_input("a", 4);
+    builder.push_input("foo", 11);
+
+    let err = builder.build().unwrap_err();
+    let err = err.downcast_ref::<InputError>().unwrap();
+    assert_eq!(err.unknown, vec!["foo".to_string()]);
+    assert_eq!(err.missing, vec!["b".to_string()]);
+    assert_eq!(err.wrong_size.len(), 1);
+    assert_eq!(err.wrong_size[0].name, "a");
+    assert_eq!(err.wrong_size[0].expected, 1);
+    assert_eq!(err.wrong_size[0].actual, 2);
+
+    // a circom 1 runtime doesn't report its input signals, they need the .sym file
+    let mut cfg = CircomConfig::<Bn254>::new(
+        "./test-vectors/mycircuit.wasm",
+        "./test-vectors/mycircuit.r1cs",
+    )?;
+    cfg.sym = None;
+    let mut builder = CircomBuilder::new(cfg);
+    builder.push_input("a", 3);
+    assert!(!builder.can_validate_inputs());
+    let err = builder.validate_inputs().unwrap_err();
+    assert!(err.to_string().contains("cannot validate"), "{}", err);
+
+    Ok(())
+}
+
 #[test]
 #[cfg(feature = "circom-2")]
 fn groth16_proof_circom2() -> Result<()> {
@@ -91,6 +358,115 @@ fn groth16_proof_circom2() -> Result<()> {
     Ok(())
 }
 
+#[test]
+#[cfg(feature = "circom-2")]
+fn circom_prover_circom2() -> Result<()> {
+    use ark_circom::{circom::R1CSFile, WitnessCalculator, ZkeyHeader};
+    use std::fs::File;
+
+    let r1cs = "./test-vectors/circom2_multiplier2.r1cs";
+    let matrices =
+        ark_circom::circom::R1CS::from(R1CSFile::<Bn254>::new(File::open(r1cs)?)?).to_matrices();
+    let pk = circom_setup(&matrices, &mut thread_rng());
+    let header = ZkeyHeader {
+        n8q: 32,
+        n8r: 32,
+        n_vars: matrices.num_instance_variables + matrices.num_witness_variables,
+        n_public: matrices.num_instance_variables - 1,
+        domain_size: (matrices.num_constraints + matrices.num_instance_variables)
+            .next_power_of_two(),
+        circuit_hash: [0; 64],
+        num_contributions: 0,
+    };
+    let wtns = WitnessCalculator::new("./test-vectors/circom2_multiplier2.wasm")?;
+    let mut prover = CircomProver::from_parts(pk, matrices, header, wtns)?.with_r1cs(r1cs)?;
+
+    let (proof, signals) = prover.prove_json(r#"{"a": 3, "b": 11}"#)?;
+    assert_eq!(signals.outputs(), &[Fr::from(33u64)]);
+    assert!(prover.verify(&proof, &signals)?);
+    assert!(!prover.verify(&proof, &[Fr::from(34u64)])?);
+
+    Ok(())
+}
+
+// the key snarkjs would set up for the circuit, with the H query of `CircomReduction`, as
+// there is no zkey of the circom 2 circuit
+#[cfg(feature = "circom-2")]
+fn circom_setup(
+    matrices: &ark_relations::r1cs::ConstraintMatrices<Fr>,
+    rng: &mut impl ark_std::rand::Rng,
+) -> ark_groth16::ProvingKey<Bn254> {
+    use ark_bn254::{G1Affine, G1Projective, G2Projective};
+    use ark_circom::CircomReduction;
+    use ark_ec::ProjectiveCurve;
+    use ark_ff::{Field, PrimeField, Zero};
+    use ark_groth16::{r1cs_to_qap::R1CStoQAP, ProvingKey, VerifyingKey};
+    use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+
+    let num_inputs = matrices.num_instance_variables;
+    let num_vars = num_inputs + matrices.num_witness_variables;
+    let domain = GeneralEvaluationDomain::<Fr>::new(matrices.num_constraints + num_inputs).unwrap();
+    let tau = domain.sample_element_outside_domain(rng);
+    let (alpha, beta, gamma, delta) = (Fr::rand(rng), Fr::rand(rng), Fr::rand(rng), Fr::rand(rng));
+
+    // A, B and C of every variable evaluated at tau
+    let lagrange = domain.evaluate_all_lagrange_coefficients(tau);
+    let (mut a, mut b, mut c) = (
+        vec![Fr::zero(); num_vars],
+        vec![Fr::zero(); num_vars],
+        vec![Fr::zero(); num_vars],
+    );
+    for (j, ((a_j, b_j), c_j)) in matrices
+        .a
+        .iter()
+        .zip(&matrices.b)
+        .zip(&matrices.c)
+        .enumerate()
+    {
+        for (evals, row) in [(&mut a, a_j), (&mut b, b_j), (&mut c, c_j)] {
+            for &(value, i) in row {
+                evals[i] += lagrange[j] * value;
+            }
+        }
+    }
+    // the constraints `x·0 = 0` of the constant and the public signals
+    for (i, a) in a.iter_mut().enumerate().take(num_inputs) {
+        *a += lagrange[matrices.num_constraints + i];
+    }
+
+    let g1 = |scalar: Fr| G1Projective::prime_subgroup_generator().mul(scalar.into_repr());
+    let g2 = |scalar: Fr| G2Projective::prime_subgroup_generator().mul(scalar.into_repr());
+    let g1_query = |scalars: &mut dyn Iterator<Item = Fr>| -> Vec<G1Affine> {
+        scalars.map(|scalar| g1(scalar).into_affine()).collect()
+    };
+    let (gamma_inv, delta_inv) = (gamma.inverse().unwrap(), delta.inverse().unwrap());
+    let lc = |i: usize| beta * a[i] + alpha * b[i] + c[i];
+    let h = CircomReduction::h_query_scalars::<Fr, GeneralEvaluationDomain<Fr>>(
+        domain.size() - 1,
+        tau,
+        domain.evaluate_vanishing_polynomial(tau),
+        delta_inv,
+    )
+    .unwrap();
+
+    ProvingKey {
+        vk: VerifyingKey {
+            alpha_g1: g1(alpha).into_affine(),
+            beta_g2: g2(beta).into_affine(),
+            gamma_g2: g2(gamma).into_affine(),
+            delta_g2: g2(delta).into_affine(),
+            gamma_abc_g1: g1_query(&mut (0..num_inputs).map(|i| lc(i) * gamma_inv)),
+        },
+        beta_g1: g1(beta).into_affine(),
+        delta_g1: g1(delta).into_affine(),
+        a_query: g1_query(&mut a.iter().copied()),
+        b_g1_query: g1_query(&mut b.iter().copied()),
+        b_g2_query: b.iter().map(|b| g2(*b).into_affine()).collect(),
+        h_query: g1_query(&mut h.into_iter()),
+        l_query: g1_query(&mut (num_inputs..num_vars).map(|i| lc(i) * delta_inv)),
+    }
+}
+
 #[test]
 #[cfg(feature = "circom-2")]
 fn witness_generation_circom2() -> Result<()> {